@@ -0,0 +1,217 @@
+use std::sync::Mutex;
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use crate::{dataset::Dataset, random::gaussian};
+
+/// Random changes made to an image every time it's drawn for training. The
+/// defaults change nothing; turn on what suits the data, e.g. no flips for
+/// digits.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Augmentations {
+    /// Zooms in on a random region whose sides are at least this fraction of
+    /// the image's, `1.0` keeps the whole image.
+    pub min_crop: f32,
+    pub flip_horizontal: bool,
+    pub flip_vertical: bool,
+    /// Largest rotation either way, in degrees.
+    pub max_rotation: f32,
+    /// Largest shift either way, as a fraction of the image's size.
+    pub max_translation: f32,
+    /// Largest value added to or subtracted from every pixel.
+    pub brightness: f32,
+    /// Largest change of the contrast factor, which scales the distance of
+    /// every pixel from the image's mean.
+    pub contrast: f32,
+    /// Standard deviation of the Gaussian noise added to every pixel.
+    pub noise: f32,
+}
+
+impl Default for Augmentations {
+    fn default() -> Augmentations {
+        Augmentations {
+            min_crop: 1.0,
+            flip_horizontal: false,
+            flip_vertical: false,
+            max_rotation: 0.0,
+            max_translation: 0.0,
+            brightness: 0.0,
+            contrast: 0.0,
+            noise: 0.0,
+        }
+    }
+}
+
+impl Augmentations {
+    /// A randomly changed copy of `pixels`, an image stored row by row with
+    /// the channels interleaved like [`crate::image::Image`] and
+    /// [`crate::idx::Idx::to_inputs`] rows. Values stay in `0..=1`.
+    pub fn apply<R: Rng>(
+        &self,
+        pixels: &[f32],
+        width: usize,
+        height: usize,
+        channels: usize,
+        rng: &mut R,
+    ) -> Vec<f32> {
+        assert_eq!(pixels.len(), width * height * channels);
+        let (w, h) = (width as f32, height as f32);
+
+        // Every output pixel is sampled from the source position found by
+        // undoing the flip, the rotation, the crop and the shift in turn
+        let crop = if self.min_crop < 1.0 {
+            rng.gen_range(self.min_crop..=1.0)
+        } else {
+            1.0
+        };
+        let (crop_x, crop_y) = (
+            symmetric(rng, (1.0 - crop) / 2.0) * w,
+            symmetric(rng, (1.0 - crop) / 2.0) * h,
+        );
+        let angle = symmetric(rng, self.max_rotation).to_radians();
+        let (shift_x, shift_y) = (
+            symmetric(rng, self.max_translation) * w,
+            symmetric(rng, self.max_translation) * h,
+        );
+        let flip_x = self.flip_horizontal && rng.gen_bool(0.5);
+        let flip_y = self.flip_vertical && rng.gen_bool(0.5);
+        let (sin, cos) = angle.sin_cos();
+
+        let mut out = vec![0.0; pixels.len()];
+        for y in 0..height {
+            for x in 0..width {
+                // relative to the centre of the image
+                let mut px = x as f32 + 0.5 - w / 2.0;
+                let mut py = y as f32 + 0.5 - h / 2.0;
+                if flip_x {
+                    px = -px;
+                }
+                if flip_y {
+                    py = -py;
+                }
+                let (rx, ry) = (cos * px + sin * py, cos * py - sin * px);
+                let sx = rx * crop + crop_x - shift_x + w / 2.0 - 0.5;
+                let sy = ry * crop + crop_y - shift_y + h / 2.0 - 0.5;
+
+                let dst = (y * width + x) * channels;
+                for c in 0..channels {
+                    out[dst + c] = bilinear(pixels, width, height, channels, sx, sy, c);
+                }
+            }
+        }
+
+        let contrast = 1.0 + symmetric(rng, self.contrast);
+        let brightness = symmetric(rng, self.brightness);
+        if contrast != 1.0 || brightness != 0.0 {
+            let mean = out.iter().sum::<f32>() / out.len().max(1) as f32;
+            for val in &mut out {
+                *val = (*val - mean) * contrast + mean + brightness;
+            }
+        }
+        if self.noise > 0.0 {
+            for val in &mut out {
+                *val += gaussian(rng) * self.noise;
+            }
+        }
+        for val in &mut out {
+            *val = val.clamp(0.0, 1.0);
+        }
+
+        out
+    }
+}
+
+/// Image samples whose inputs get randomly augmented every time they're
+/// fetched, so every epoch sees slightly different images. Outputs are left
+/// alone.
+///
+/// The random generator is seeded, so a run can be repeated exactly as long
+/// as samples are fetched in the same order.
+pub struct Augmented<D> {
+    pub inner: D,
+    pub width: usize,
+    pub height: usize,
+    pub channels: usize,
+    pub augmentations: Augmentations,
+    rng: Mutex<StdRng>,
+}
+
+impl<D: Dataset> Augmented<D> {
+    pub fn new(
+        inner: D,
+        (width, height, channels): (usize, usize, usize),
+        augmentations: Augmentations,
+        seed: u64,
+    ) -> Augmented<D> {
+        assert_eq!(inner.input_cols(), width * height * channels);
+        Augmented {
+            inner,
+            width,
+            height,
+            channels,
+            augmentations,
+            rng: Mutex::new(StdRng::seed_from_u64(seed)),
+        }
+    }
+}
+
+impl<D: Dataset> Dataset for Augmented<D> {
+    fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    fn input_cols(&self) -> usize {
+        self.inner.input_cols()
+    }
+
+    fn output_cols(&self) -> usize {
+        self.inner.output_cols()
+    }
+
+    fn get(&self, index: usize) -> (Vec<f32>, Vec<f32>) {
+        let (input, output) = self.inner.get(index);
+        let mut rng = self.rng.lock().unwrap();
+        let input =
+            self.augmentations
+                .apply(&input, self.width, self.height, self.channels, &mut *rng);
+        (input, output)
+    }
+
+    fn original(&self) -> Option<&dyn Dataset> {
+        Some(&self.inner)
+    }
+}
+
+// Uniform in -max..=max, without touching the generator when turned off
+fn symmetric<R: Rng>(rng: &mut R, max: f32) -> f32 {
+    if max > 0.0 {
+        rng.gen_range(-max..=max)
+    } else {
+        0.0
+    }
+}
+
+// Channel `c` at a fractional position, zero outside the image
+fn bilinear(
+    pixels: &[f32],
+    width: usize,
+    height: usize,
+    channels: usize,
+    x: f32,
+    y: f32,
+    c: usize,
+) -> f32 {
+    let (x0, y0) = (x.floor(), y.floor());
+    let (fx, fy) = (x - x0, y - y0);
+    let at = |x: f32, y: f32| -> f32 {
+        if x < 0.0 || y < 0.0 || x >= width as f32 || y >= height as f32 {
+            0.0
+        } else {
+            pixels[(y as usize * width + x as usize) * channels + c]
+        }
+    };
+
+    let top = at(x0, y0) * (1.0 - fx) + at(x0 + 1.0, y0) * fx;
+    let bottom = at(x0, y0 + 1.0) * (1.0 - fx) + at(x0 + 1.0, y0 + 1.0) * fx;
+    top * (1.0 - fy) + bottom * fy
+}
@@ -0,0 +1,412 @@
+use std::{fmt, fs, io, path::Path};
+
+use serde_json::{json, Map, Value};
+
+use crate::{
+    augment::Augmentations,
+    preprocess::{Pipeline, Transform},
+};
+
+// What the framework implements so far, anything else is rejected
+const ACTIVATIONS: &[&str] = &["sigmoid"];
+const LOSSES: &[&str] = &["mse", "cross_entropy"];
+const OPTIMIZERS: &[&str] = &["sgd", "momentum"];
+const TRANSFORMS: &[&str] = &[
+    "min_max",
+    "standard",
+    "robust",
+    "one_hot",
+    "label",
+    "polynomial",
+];
+
+/// Settings for one training run, read from a JSON file like
+///
+/// ```json
+/// {
+///     "hidden": [4, 4],
+///     "activation": "sigmoid",
+///     "loss": "mse",
+///     "optimizer": "sgd",
+///     "learning_rate": 1.0,
+///     "epochs": 100000,
+///     "batch_size": 0,
+///     "validation": 0.2,
+///     "input_pipeline": ["standard", {"type": "polynomial", "degree": 2}],
+///     "output_pipeline": ["min_max"],
+///     "augmentation": {"max_rotation": 10, "max_translation": 0.1, "noise": 0.05},
+///     "dataset": "xor",
+///     "seed": 42
+/// }
+/// ```
+///
+/// Every field is optional. The input and output layers are sized from the
+/// data, so only the hidden layers are configured. A pipeline step is a
+/// transform's name or an object with its `type` and settings, `column` for
+/// `one_hot` and `label` and `degree` for `polynomial`. `augmentation` sets
+/// any of the [`Augmentations`] fields by name.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Config {
+    /// Hidden layer widths, `None` leaves them to the data set.
+    pub hidden: Option<Vec<usize>>,
+    /// One activation for every layer, or one per non-input layer.
+    pub activations: Vec<String>,
+    pub loss: String,
+    pub optimizer: String,
+    pub learning_rate: f32,
+    pub epochs: usize,
+    /// Samples per gradient step, 0 for the whole data set, `None` for the
+    /// data set's default.
+    pub batch_size: Option<usize>,
+    /// Share of the rows held out to measure overfitting on, 0 trains on all
+    /// of them.
+    pub validation: f32,
+    /// Unfitted transforms for the inputs, fitted on the training rows.
+    pub input_pipeline: Pipeline,
+    /// Unfitted transforms for the targets, undone on predictions.
+    pub output_pipeline: Pipeline,
+    /// Random changes to image inputs, the defaults change nothing.
+    pub augmentation: Augmentations,
+    /// A built-in data set's name or a path to load.
+    pub dataset: Option<String>,
+    /// Seeds the initial weights and the data splits, random when `None`.
+    pub seed: Option<u64>,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            hidden: None,
+            activations: vec!["sigmoid".to_owned()],
+            loss: "mse".to_owned(),
+            optimizer: "sgd".to_owned(),
+            learning_rate: 1.0,
+            epochs: 100_000,
+            batch_size: None,
+            validation: 0.0,
+            input_pipeline: Pipeline::default(),
+            output_pipeline: Pipeline::default(),
+            augmentation: Augmentations::default(),
+            dataset: None,
+            seed: None,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(io::Error),
+    Json(serde_json::Error),
+    /// A field with a bad value, named by its path like `hidden[1]`, or an
+    /// empty path for the whole document.
+    Field {
+        field: String,
+        message: String,
+    },
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Io(err) => write!(f, "{}", err),
+            ConfigError::Json(err) => write!(f, "{}", err),
+            ConfigError::Field { field, message } if field.is_empty() => write!(f, "{}", message),
+            ConfigError::Field { field, message } => write!(f, "`{}`: {}", field, message),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl From<io::Error> for ConfigError {
+    fn from(err: io::Error) -> ConfigError {
+        ConfigError::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for ConfigError {
+    fn from(err: serde_json::Error) -> ConfigError {
+        ConfigError::Json(err)
+    }
+}
+
+impl Config {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Config, ConfigError> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    pub fn parse(text: &str) -> Result<Config, ConfigError> {
+        let value: Value = serde_json::from_str(text)?;
+        let fields = value
+            .as_object()
+            .ok_or_else(|| field_error("", "the config must be a JSON object"))?;
+        let mut config = Config::default();
+        // `activation` or `activations`, whichever the file used
+        let mut activations_key = "activations";
+
+        for (key, value) in fields {
+            match key.as_str() {
+                "hidden" => {
+                    let widths = array(value, key)?
+                        .iter()
+                        .enumerate()
+                        .map(|(i, width)| match width.as_u64() {
+                            Some(width) if width > 0 => Ok(width as usize),
+                            _ => Err(field_error(
+                                &format!("hidden[{}]", i),
+                                "must be a positive integer",
+                            )),
+                        })
+                        .collect::<Result<_, _>>()?;
+                    config.hidden = Some(widths);
+                }
+                "activation" | "activations" => {
+                    config.activations = match value {
+                        Value::Array(names) => names
+                            .iter()
+                            .enumerate()
+                            .map(|(i, name)| choice(name, &format!("{}[{}]", key, i), ACTIVATIONS))
+                            .collect::<Result<_, _>>()?,
+                        name => vec![choice(name, key, ACTIVATIONS)?],
+                    };
+                    if config.activations.is_empty() {
+                        return Err(field_error(key, "must name at least one activation"));
+                    }
+                    activations_key = key;
+                }
+                "loss" => config.loss = choice(value, key, LOSSES)?,
+                "optimizer" => config.optimizer = choice(value, key, OPTIMIZERS)?,
+                "learning_rate" => {
+                    config.learning_rate = match value.as_f64() {
+                        Some(rate) if rate > 0.0 && rate.is_finite() => rate as f32,
+                        _ => return Err(field_error(key, "must be a positive number")),
+                    }
+                }
+                "epochs" => {
+                    config.epochs = match value.as_u64() {
+                        Some(epochs) if epochs > 0 => epochs as usize,
+                        _ => return Err(field_error(key, "must be a positive integer")),
+                    }
+                }
+                "batch_size" => {
+                    config.batch_size = Some(
+                        value
+                            .as_u64()
+                            .ok_or_else(|| field_error(key, "must be a non-negative integer"))?
+                            as usize,
+                    )
+                }
+                "validation" => {
+                    config.validation = match value.as_f64() {
+                        Some(ratio) if (0.0..1.0).contains(&ratio) => ratio as f32,
+                        _ => return Err(field_error(key, "must be a number in 0..1")),
+                    }
+                }
+                "input_pipeline" => config.input_pipeline = pipeline(value, key)?,
+                "output_pipeline" => config.output_pipeline = pipeline(value, key)?,
+                "augmentation" => config.augmentation = augmentations(value, key)?,
+                "dataset" => {
+                    config.dataset = Some(
+                        value
+                            .as_str()
+                            .ok_or_else(|| field_error(key, "must be a string"))?
+                            .to_owned(),
+                    )
+                }
+                "seed" => {
+                    config.seed = Some(
+                        value
+                            .as_u64()
+                            .ok_or_else(|| field_error(key, "must be a non-negative integer"))?,
+                    )
+                }
+                _ => return Err(field_error(key, "unknown field")),
+            }
+        }
+
+        if let Some(hidden) = &config.hidden {
+            let layers = hidden.len() + 1;
+            if config.activations.len() != 1 && config.activations.len() != layers {
+                return Err(field_error(
+                    activations_key,
+                    &format!(
+                        "expected 1 activation or {} (one per hidden and output layer), found {}",
+                        layers,
+                        config.activations.len()
+                    ),
+                ));
+            }
+        }
+
+        Ok(config)
+    }
+
+    pub fn to_json(&self) -> Value {
+        let mut fields = Map::new();
+        if let Some(hidden) = &self.hidden {
+            fields.insert("hidden".to_owned(), hidden.clone().into());
+        }
+        fields.insert("activations".to_owned(), self.activations.clone().into());
+        fields.insert("loss".to_owned(), self.loss.clone().into());
+        fields.insert("optimizer".to_owned(), self.optimizer.clone().into());
+        fields.insert("learning_rate".to_owned(), self.learning_rate.into());
+        fields.insert("epochs".to_owned(), self.epochs.into());
+        if let Some(batch_size) = self.batch_size {
+            fields.insert("batch_size".to_owned(), batch_size.into());
+        }
+        fields.insert("validation".to_owned(), self.validation.into());
+        for (key, pipeline) in [
+            ("input_pipeline", &self.input_pipeline),
+            ("output_pipeline", &self.output_pipeline),
+        ] {
+            if !pipeline.is_empty() {
+                let steps = pipeline.steps.iter().map(step_json).collect();
+                fields.insert(key.to_owned(), Value::Array(steps));
+            }
+        }
+        if self.augmentation != Augmentations::default() {
+            let a = &self.augmentation;
+            fields.insert(
+                "augmentation".to_owned(),
+                json!({
+                    "min_crop": a.min_crop,
+                    "flip_horizontal": a.flip_horizontal,
+                    "flip_vertical": a.flip_vertical,
+                    "max_rotation": a.max_rotation,
+                    "max_translation": a.max_translation,
+                    "brightness": a.brightness,
+                    "contrast": a.contrast,
+                    "noise": a.noise,
+                }),
+            );
+        }
+        if let Some(dataset) = &self.dataset {
+            fields.insert("dataset".to_owned(), dataset.clone().into());
+        }
+        if let Some(seed) = self.seed {
+            fields.insert("seed".to_owned(), seed.into());
+        }
+        Value::Object(fields)
+    }
+}
+
+fn field_error(field: &str, message: &str) -> ConfigError {
+    ConfigError::Field {
+        field: field.to_owned(),
+        message: message.to_owned(),
+    }
+}
+
+fn array<'a>(value: &'a Value, field: &str) -> Result<&'a Vec<Value>, ConfigError> {
+    value
+        .as_array()
+        .ok_or_else(|| field_error(field, "must be an array"))
+}
+
+fn augmentations(value: &Value, field: &str) -> Result<Augmentations, ConfigError> {
+    let settings = value
+        .as_object()
+        .ok_or_else(|| field_error(field, "must be an object"))?;
+    let mut augmentations = Augmentations::default();
+    for (key, value) in settings {
+        let path = format!("{}.{}", field, key);
+        let flag = || {
+            value
+                .as_bool()
+                .ok_or_else(|| field_error(&path, "must be true or false"))
+        };
+        // a number in `min..=max`
+        let number = |min: f64, max: f64| match value.as_f64() {
+            Some(number) if (min..=max).contains(&number) => Ok(number as f32),
+            _ => Err(field_error(
+                &path,
+                &format!("must be a number in {}..={}", min, max),
+            )),
+        };
+
+        match key.as_str() {
+            "min_crop" => match value.as_f64() {
+                Some(crop) if crop > 0.0 && crop <= 1.0 => augmentations.min_crop = crop as f32,
+                _ => return Err(field_error(&path, "must be a number above 0 and up to 1")),
+            },
+            "flip_horizontal" => augmentations.flip_horizontal = flag()?,
+            "flip_vertical" => augmentations.flip_vertical = flag()?,
+            "max_rotation" => augmentations.max_rotation = number(0.0, 180.0)?,
+            "max_translation" => augmentations.max_translation = number(0.0, 1.0)?,
+            "brightness" => augmentations.brightness = number(0.0, 1.0)?,
+            "contrast" => augmentations.contrast = number(0.0, 1.0)?,
+            "noise" => augmentations.noise = number(0.0, 1.0)?,
+            _ => return Err(field_error(&path, "unknown field")),
+        }
+    }
+    Ok(augmentations)
+}
+
+fn pipeline(value: &Value, field: &str) -> Result<Pipeline, ConfigError> {
+    let steps = array(value, field)?
+        .iter()
+        .enumerate()
+        .map(|(i, step)| transform(step, &format!("{}[{}]", field, i)))
+        .collect::<Result<_, _>>()?;
+    Ok(Pipeline::new(steps))
+}
+
+// An unfitted transform from its name or an object with its settings
+fn transform(value: &Value, field: &str) -> Result<Transform, ConfigError> {
+    let name = match value {
+        Value::Object(settings) => settings.get("type").unwrap_or(&Value::Null),
+        name => name,
+    };
+    let setting = |key: &str, min: u64| match value[key].as_u64() {
+        Some(setting) if setting >= min => Ok(setting as usize),
+        _ => Err(field_error(
+            &format!("{}.{}", field, key),
+            if min == 0 {
+                "must be a non-negative integer"
+            } else {
+                "must be a positive integer"
+            },
+        )),
+    };
+
+    match choice(name, field, TRANSFORMS)?.as_str() {
+        "min_max" => Ok(Transform::min_max()),
+        "standard" => Ok(Transform::standard()),
+        "robust" => Ok(Transform::robust()),
+        "one_hot" => Ok(Transform::one_hot(setting("column", 0)?)),
+        "label" => Ok(Transform::label(setting("column", 0)?)),
+        "polynomial" => Ok(Transform::polynomial(setting("degree", 1)?)),
+        _ => unreachable!("every transform is listed"),
+    }
+}
+
+// The settings `transform` reads back, without the fitted state
+fn step_json(step: &Transform) -> Value {
+    match step {
+        Transform::MinMax { .. } => "min_max".into(),
+        Transform::Standard { .. } => "standard".into(),
+        Transform::Robust { .. } => "robust".into(),
+        Transform::OneHot { column, .. } => json!({"type": "one_hot", "column": column}),
+        Transform::Label { column, .. } => json!({"type": "label", "column": column}),
+        Transform::Polynomial { degree, .. } => json!({"type": "polynomial", "degree": degree}),
+    }
+}
+
+// One of the supported names
+fn choice(value: &Value, field: &str, supported: &[&str]) -> Result<String, ConfigError> {
+    let name = value
+        .as_str()
+        .ok_or_else(|| field_error(field, "must be a string"))?;
+    if supported.contains(&name) {
+        Ok(name.to_owned())
+    } else {
+        Err(field_error(
+            field,
+            &format!(
+                "unsupported value `{}`, expected one of: {}",
+                name,
+                supported.join(", ")
+            ),
+        ))
+    }
+}
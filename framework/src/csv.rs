@@ -0,0 +1,566 @@
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    fmt, fs, io,
+    path::Path,
+};
+
+use serde_json::{json, Value};
+
+use crate::Mat;
+
+/// A column picked by its header name or its 0-based position.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Column {
+    Name(String),
+    Index(usize),
+}
+
+impl From<&str> for Column {
+    fn from(name: &str) -> Column {
+        Column::Name(name.to_owned())
+    }
+}
+
+impl From<usize> for Column {
+    fn from(index: usize) -> Column {
+        Column::Index(index)
+    }
+}
+
+/// What to do with empty fields and `NA`, `N/A`, `NaN` or `?`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Missing {
+    Error,
+    SkipRow,
+    /// Use this value instead. Categorical columns get all zeros.
+    Fill(f32),
+    /// Use the column's mean, or its most common value for categorical
+    /// columns.
+    Mean,
+}
+
+#[derive(Clone, Debug)]
+pub struct CsvOptions {
+    pub has_header: bool,
+    pub delimiter: char,
+    /// Empty means every column that isn't a target.
+    pub inputs: Vec<Column>,
+    /// Empty means the last column.
+    pub targets: Vec<Column>,
+    pub missing: Missing,
+}
+
+impl Default for CsvOptions {
+    fn default() -> CsvOptions {
+        CsvOptions {
+            has_header: true,
+            delimiter: ',',
+            inputs: Vec::new(),
+            targets: Vec::new(),
+            missing: Missing::Error,
+        }
+    }
+}
+
+/// Loaded rows ready for [`crate::NN::backprop`].
+///
+/// Columns that aren't all numbers are one-hot encoded, so they take up one
+/// matrix column per distinct value, named `column=value`.
+#[derive(Clone, Debug)]
+pub struct CsvData {
+    pub t_input: Mat,
+    pub t_output: Mat,
+    pub input_names: Vec<String>,
+    pub output_names: Vec<String>,
+    /// How the columns were turned into numbers, to encode other files the
+    /// same way with [`parse_with_schema`].
+    pub schema: Schema,
+}
+
+/// How one column of a file becomes matrix columns.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ColumnEncoding {
+    /// Parsed as is, `fill` standing in for missing values.
+    Numeric { fill: f32 },
+    /// One-hot over the sorted `categories`, missing values going to `fill`
+    /// or to all zeros. So do values that aren't among the categories.
+    Categorical {
+        categories: Vec<String>,
+        fill: Option<usize>,
+    },
+}
+
+/// The encodings of the input and target columns, in the order they were
+/// picked.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Schema {
+    pub inputs: Vec<ColumnEncoding>,
+    pub targets: Vec<ColumnEncoding>,
+}
+
+#[derive(Debug)]
+pub enum CsvError {
+    Io(io::Error),
+    Empty,
+    UnknownColumn(Column),
+    /// A line with a different number of fields than the first one.
+    Ragged {
+        line: usize,
+        expected: usize,
+        found: usize,
+    },
+    MissingValue {
+        line: usize,
+        column: String,
+    },
+    /// Text in a column encoded as numbers.
+    NotANumber {
+        line: usize,
+        column: String,
+    },
+    /// Columns picked don't match the schema the file is read with.
+    Schema(String),
+}
+
+impl fmt::Display for CsvError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CsvError::Io(err) => write!(f, "{}", err),
+            CsvError::Empty => write!(f, "no data rows"),
+            CsvError::UnknownColumn(Column::Name(name)) => write!(f, "no column named `{}`", name),
+            CsvError::UnknownColumn(Column::Index(index)) => {
+                write!(f, "column index {} is out of range", index)
+            }
+            CsvError::Ragged {
+                line,
+                expected,
+                found,
+            } => write!(
+                f,
+                "line {}: expected {} fields, found {}",
+                line, expected, found
+            ),
+            CsvError::MissingValue { line, column } => {
+                write!(f, "line {}: missing value in column `{}`", line, column)
+            }
+            CsvError::NotANumber { line, column } => {
+                write!(f, "line {}: column `{}` must hold numbers", line, column)
+            }
+            CsvError::Schema(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl std::error::Error for CsvError {}
+
+impl From<io::Error> for CsvError {
+    fn from(err: io::Error) -> CsvError {
+        CsvError::Io(err)
+    }
+}
+
+pub fn load<P: AsRef<Path>>(path: P, options: &CsvOptions) -> Result<CsvData, CsvError> {
+    parse(&fs::read_to_string(path)?, options)
+}
+
+pub fn parse(text: &str, options: &CsvOptions) -> Result<CsvData, CsvError> {
+    let table = Table::read(text, options, true)?;
+
+    let mut encoders = HashMap::new();
+    for &col in table.inputs.iter().chain(&table.targets) {
+        if let Entry::Vacant(entry) = encoders.entry(col) {
+            entry.insert(ColumnEncoding::fit(
+                &table.rows,
+                col,
+                &table.names[col],
+                options.missing,
+            )?);
+        }
+    }
+    let schema = Schema {
+        inputs: table
+            .inputs
+            .iter()
+            .map(|col| encoders[col].clone())
+            .collect(),
+        targets: table
+            .targets
+            .iter()
+            .map(|col| encoders[col].clone())
+            .collect(),
+    };
+
+    table.encode(schema)
+}
+
+/// Like [`parse`], but encodes the columns the way `schema` says instead of
+/// fitting encodings to this file, so the matrices line up with the ones
+/// the schema came from. A schema without targets reads files without them,
+/// every column being an input unless `options` picks them.
+pub fn parse_with_schema(
+    text: &str,
+    options: &CsvOptions,
+    schema: &Schema,
+) -> Result<CsvData, CsvError> {
+    let table = Table::read(text, options, !schema.targets.is_empty())?;
+    for (kind, picked, expected) in [
+        ("input", table.inputs.len(), schema.inputs.len()),
+        ("target", table.targets.len(), schema.targets.len()),
+    ] {
+        if picked != expected {
+            return Err(CsvError::Schema(format!(
+                "expected {} {} columns, found {}",
+                expected, kind, picked
+            )));
+        }
+    }
+
+    table.encode(schema.clone())
+}
+
+/// Number of fields on the first line, the header when there is one.
+pub fn field_count(text: &str, options: &CsvOptions) -> Result<usize, CsvError> {
+    text.lines()
+        .find(|line| !line.trim().is_empty())
+        .map(|line| split_line(line, options.delimiter).len())
+        .ok_or(CsvError::Empty)
+}
+
+// The rows of a file and the columns picked from them
+struct Table {
+    names: Vec<String>,
+    // (line number, fields)
+    rows: Vec<(usize, Vec<String>)>,
+    inputs: Vec<usize>,
+    targets: Vec<usize>,
+}
+
+impl Table {
+    // `default_target` picks the last column when `options` picks no targets
+    fn read(text: &str, options: &CsvOptions, default_target: bool) -> Result<Table, CsvError> {
+        // (line number, fields) of every non-blank line
+        let mut lines = text
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(i, line)| (i + 1, split_line(line, options.delimiter)));
+
+        let header = if options.has_header {
+            lines.next().map(|(_, fields)| fields)
+        } else {
+            None
+        };
+        let mut rows: Vec<(usize, Vec<String>)> = lines.collect();
+
+        let width = match (&header, rows.first()) {
+            (Some(header), _) => header.len(),
+            (None, Some((_, fields))) => fields.len(),
+            (None, None) => return Err(CsvError::Empty),
+        };
+        for (line, fields) in &rows {
+            if fields.len() != width {
+                return Err(CsvError::Ragged {
+                    line: *line,
+                    expected: width,
+                    found: fields.len(),
+                });
+            }
+        }
+
+        let names: Vec<String> = match header {
+            Some(header) => header,
+            None => (0..width).map(|i| i.to_string()).collect(),
+        };
+        let find = |column: &Column| -> Result<usize, CsvError> {
+            match column {
+                Column::Index(index) if *index < width => Ok(*index),
+                Column::Name(name) => names
+                    .iter()
+                    .position(|n| n == name)
+                    .ok_or_else(|| CsvError::UnknownColumn(column.clone())),
+                _ => Err(CsvError::UnknownColumn(column.clone())),
+            }
+        };
+
+        let targets: Vec<usize> = if options.targets.is_empty() && default_target {
+            vec![width - 1]
+        } else {
+            options.targets.iter().map(find).collect::<Result<_, _>>()?
+        };
+        let inputs: Vec<usize> = if options.inputs.is_empty() {
+            (0..width).filter(|i| !targets.contains(i)).collect()
+        } else {
+            options.inputs.iter().map(find).collect::<Result<_, _>>()?
+        };
+
+        if options.missing == Missing::SkipRow {
+            rows.retain(|(_, fields)| {
+                !inputs
+                    .iter()
+                    .chain(&targets)
+                    .any(|&col| is_missing(&fields[col]))
+            });
+        }
+        if rows.is_empty() {
+            return Err(CsvError::Empty);
+        }
+
+        Ok(Table {
+            names,
+            rows,
+            inputs,
+            targets,
+        })
+    }
+
+    fn encode(&self, schema: Schema) -> Result<CsvData, CsvError> {
+        let build = |cols: &[usize],
+                     encodings: &[ColumnEncoding]|
+         -> Result<(Mat, Vec<String>), CsvError> {
+            let mut names_out = Vec::new();
+            for (col, encoding) in cols.iter().zip(encodings) {
+                names_out.extend(encoding.names(&self.names[*col]));
+            }
+
+            let mut data = Vec::with_capacity(self.rows.len());
+            for (line, fields) in &self.rows {
+                let mut row = Vec::with_capacity(names_out.len());
+                for (col, encoding) in cols.iter().zip(encodings) {
+                    encoding.encode(&fields[*col], &mut row).ok_or_else(|| {
+                        CsvError::NotANumber {
+                            line: *line,
+                            column: self.names[*col].clone(),
+                        }
+                    })?;
+                }
+                data.push(row);
+            }
+
+            let mat = Mat {
+                rows: data.len(),
+                cols: names_out.len(),
+                data,
+            };
+            Ok((mat, names_out))
+        };
+
+        let (t_input, input_names) = build(&self.inputs, &schema.inputs)?;
+        let (t_output, output_names) = build(&self.targets, &schema.targets)?;
+        Ok(CsvData {
+            t_input,
+            t_output,
+            input_names,
+            output_names,
+            schema,
+        })
+    }
+}
+
+impl ColumnEncoding {
+    fn fit(
+        rows: &[(usize, Vec<String>)],
+        col: usize,
+        name: &str,
+        missing: Missing,
+    ) -> Result<ColumnEncoding, CsvError> {
+        let present: Vec<&str> = rows
+            .iter()
+            .map(|(_, fields)| fields[col].as_str())
+            .filter(|field| !is_missing(field))
+            .collect();
+
+        if missing == Missing::Error {
+            if let Some((line, _)) = rows.iter().find(|(_, fields)| is_missing(&fields[col])) {
+                return Err(CsvError::MissingValue {
+                    line: *line,
+                    column: name.to_owned(),
+                });
+            }
+        }
+
+        let numbers: Option<Vec<f32>> = present.iter().map(|field| field.parse().ok()).collect();
+        if let Some(numbers) = numbers {
+            let fill = match missing {
+                Missing::Fill(val) => val,
+                Missing::Mean if !numbers.is_empty() => {
+                    numbers.iter().sum::<f32>() / numbers.len() as f32
+                }
+                _ => 0.0,
+            };
+            return Ok(ColumnEncoding::Numeric { fill });
+        }
+
+        let mut counts: HashMap<&str, usize> = HashMap::new();
+        for field in &present {
+            *counts.entry(field).or_insert(0) += 1;
+        }
+        let mut categories: Vec<String> = counts.keys().map(|c| c.to_string()).collect();
+        categories.sort();
+
+        let fill = match missing {
+            Missing::Mean => {
+                // most common, ties going to the first in sorted order
+                let mut best = 0;
+                for (i, category) in categories.iter().enumerate() {
+                    if counts[category.as_str()] > counts[categories[best].as_str()] {
+                        best = i;
+                    }
+                }
+                Some(best)
+            }
+            _ => None,
+        };
+        Ok(ColumnEncoding::Categorical { categories, fill })
+    }
+
+    /// Matrix columns this column takes.
+    pub fn width(&self) -> usize {
+        match self {
+            ColumnEncoding::Numeric { .. } => 1,
+            ColumnEncoding::Categorical { categories, .. } => categories.len(),
+        }
+    }
+
+    fn names(&self, name: &str) -> Vec<String> {
+        match self {
+            ColumnEncoding::Numeric { .. } => vec![name.to_owned()],
+            ColumnEncoding::Categorical { categories, .. } => categories
+                .iter()
+                .map(|category| format!("{}={}", name, category))
+                .collect(),
+        }
+    }
+
+    // `None` for text in a numeric column
+    fn encode(&self, field: &str, row: &mut Vec<f32>) -> Option<()> {
+        match self {
+            ColumnEncoding::Numeric { fill } => {
+                if is_missing(field) {
+                    row.push(*fill);
+                } else {
+                    row.push(field.parse().ok()?);
+                }
+            }
+            ColumnEncoding::Categorical { categories, fill } => {
+                let index = if is_missing(field) {
+                    *fill
+                } else {
+                    categories.iter().position(|c| c == field)
+                };
+                row.extend((0..categories.len()).map(|i| (Some(i) == index) as i32 as f32));
+            }
+        }
+        Some(())
+    }
+
+    pub fn to_json(&self) -> Value {
+        match self {
+            ColumnEncoding::Numeric { fill } => json!({"type": "numeric", "fill": fill}),
+            ColumnEncoding::Categorical { categories, fill } => {
+                json!({"type": "categorical", "categories": categories, "fill": fill})
+            }
+        }
+    }
+
+    pub fn from_json(value: &Value) -> Result<ColumnEncoding, String> {
+        match value["type"].as_str() {
+            Some("numeric") => Ok(ColumnEncoding::Numeric {
+                fill: value["fill"].as_f64().ok_or("`fill` must be a number")? as f32,
+            }),
+            Some("categorical") => {
+                let categories: Vec<String> = value["categories"]
+                    .as_array()
+                    .ok_or("`categories` must be an array")?
+                    .iter()
+                    .map(|c| c.as_str().map(str::to_owned))
+                    .collect::<Option<_>>()
+                    .ok_or("`categories` must hold strings")?;
+                let fill = match &value["fill"] {
+                    Value::Null => None,
+                    fill => Some(
+                        fill.as_u64()
+                            .map(|i| i as usize)
+                            .filter(|i| *i < categories.len())
+                            .ok_or("`fill` must be the index of a category")?,
+                    ),
+                };
+                Ok(ColumnEncoding::Categorical { categories, fill })
+            }
+            Some(other) => Err(format!("unknown column encoding `{}`", other)),
+            None => Err("column encoding without a `type`".to_owned()),
+        }
+    }
+}
+
+impl Schema {
+    /// Plain numbers in every column, missing ones read as 0.
+    pub fn numeric(inputs: usize, targets: usize) -> Schema {
+        let column = ColumnEncoding::Numeric { fill: 0.0 };
+        Schema {
+            inputs: vec![column.clone(); inputs],
+            targets: vec![column; targets],
+        }
+    }
+
+    /// Matrix columns of the inputs.
+    pub fn input_width(&self) -> usize {
+        self.inputs.iter().map(ColumnEncoding::width).sum()
+    }
+
+    /// Matrix columns of the targets.
+    pub fn target_width(&self) -> usize {
+        self.targets.iter().map(ColumnEncoding::width).sum()
+    }
+
+    pub fn to_json(&self) -> Value {
+        json!({
+            "inputs": self.inputs.iter().map(ColumnEncoding::to_json).collect::<Vec<_>>(),
+            "targets": self.targets.iter().map(ColumnEncoding::to_json).collect::<Vec<_>>(),
+        })
+    }
+
+    pub fn from_json(value: &Value) -> Result<Schema, String> {
+        let encodings = |key: &str| -> Result<Vec<ColumnEncoding>, String> {
+            value[key]
+                .as_array()
+                .ok_or_else(|| format!("`{}` must be an array", key))?
+                .iter()
+                .map(ColumnEncoding::from_json)
+                .collect()
+        };
+        Ok(Schema {
+            inputs: encodings("inputs")?,
+            targets: encodings("targets")?,
+        })
+    }
+}
+
+fn is_missing(field: &str) -> bool {
+    matches!(field, "" | "NA" | "N/A" | "NaN" | "?")
+}
+
+// Splits on `delimiter` outside of double quotes and trims every field
+fn split_line(line: &str, delimiter: char) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            c if c == delimiter && !quoted => {
+                fields.push(field.trim().to_owned());
+                field.clear();
+            }
+            c => field.push(c),
+        }
+    }
+    fields.push(field.trim().to_owned());
+
+    fields
+}
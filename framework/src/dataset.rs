@@ -0,0 +1,319 @@
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader, Seek, SeekFrom},
+    path::Path,
+    sync::Mutex,
+};
+
+use crate::{
+    loss::{Loss, Mse},
+    Mat, NN,
+};
+
+/// Rows of `(input, output)` training samples.
+///
+/// Only [`Dataset::get`] has to be implemented, batches are assembled from
+/// it, so samples never have to be in memory all at once.
+pub trait Dataset {
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn input_cols(&self) -> usize;
+
+    fn output_cols(&self) -> usize;
+
+    /// The `(input, output)` values of sample `index`.
+    fn get(&self, index: usize) -> (Vec<f32>, Vec<f32>);
+
+    /// The samples before any random changes, which the cost is measured on
+    /// so it doesn't jump around with them. `None` when the samples don't
+    /// change.
+    fn original(&self) -> Option<&dyn Dataset> {
+        None
+    }
+
+    /// Samples `start..end` as an input and an output matrix.
+    fn batch(&self, start: usize, end: usize) -> (Mat, Mat) {
+        assert!(start <= end && end <= self.len());
+        let mut input = Mat {
+            rows: end - start,
+            cols: self.input_cols(),
+            data: Vec::with_capacity(end - start),
+        };
+        let mut output = Mat {
+            rows: end - start,
+            cols: self.output_cols(),
+            data: Vec::with_capacity(end - start),
+        };
+
+        for i in start..end {
+            let (x, y) = self.get(i);
+            assert_eq!(x.len(), input.cols);
+            assert_eq!(y.len(), output.cols);
+            input.data.push(x);
+            output.data.push(y);
+        }
+
+        (input, output)
+    }
+
+    /// Iterates over consecutive batches of `batch_size` samples, the last
+    /// one holding whatever is left.
+    fn batches(&self, batch_size: usize) -> Batches<'_, Self>
+    where
+        Self: Sized,
+    {
+        assert!(batch_size > 0);
+        Batches {
+            dataset: self,
+            batch_size,
+            next: 0,
+        }
+    }
+}
+
+pub struct Batches<'a, D: ?Sized> {
+    dataset: &'a D,
+    batch_size: usize,
+    next: usize,
+}
+
+impl<'a, D: Dataset + ?Sized> Iterator for Batches<'a, D> {
+    type Item = (Mat, Mat);
+
+    fn next(&mut self) -> Option<(Mat, Mat)> {
+        if self.next >= self.dataset.len() {
+            return None;
+        }
+
+        let start = self.next;
+        self.next = (start + self.batch_size).min(self.dataset.len());
+        Some(self.dataset.batch(start, self.next))
+    }
+}
+
+impl<D: Dataset + ?Sized> Dataset for Box<D> {
+    fn len(&self) -> usize {
+        (**self).len()
+    }
+
+    fn input_cols(&self) -> usize {
+        (**self).input_cols()
+    }
+
+    fn output_cols(&self) -> usize {
+        (**self).output_cols()
+    }
+
+    fn get(&self, index: usize) -> (Vec<f32>, Vec<f32>) {
+        (**self).get(index)
+    }
+
+    fn original(&self) -> Option<&dyn Dataset> {
+        (**self).original()
+    }
+
+    fn batch(&self, start: usize, end: usize) -> (Mat, Mat) {
+        (**self).batch(start, end)
+    }
+}
+
+/// Total cost of `nn` over every sample, like [`NN::cost`] on one big matrix.
+/// Measured on the [`Dataset::original`] samples when there are any.
+pub fn cost<D: Dataset + ?Sized>(nn: &NN, data: &D) -> f32 {
+    loss_cost(nn, data, &Mse)
+}
+
+/// Like [`cost`], measured with `loss` instead of the squared error.
+pub fn loss_cost<D: Dataset + ?Sized>(nn: &NN, data: &D, loss: &dyn Loss) -> f32 {
+    // Big enough to keep the per-batch overhead low
+    const COST_BATCH: usize = 256;
+    if let Some(original) = data.original() {
+        return loss_cost(nn, original, loss);
+    }
+    (0..data.len())
+        .step_by(COST_BATCH)
+        .map(|start| {
+            let (input, output) = data.batch(start, (start + COST_BATCH).min(data.len()));
+            NN::loss_cost(nn, &input, &output, loss)
+        })
+        .sum()
+}
+
+/// The `Mat` pair every example used so far.
+#[derive(Clone, Debug)]
+pub struct InMemory {
+    pub input: Mat,
+    pub output: Mat,
+}
+
+impl InMemory {
+    pub fn new(input: Mat, output: Mat) -> InMemory {
+        assert_eq!(input.rows, output.rows);
+        InMemory { input, output }
+    }
+}
+
+impl Dataset for InMemory {
+    fn len(&self) -> usize {
+        self.input.rows
+    }
+
+    fn input_cols(&self) -> usize {
+        self.input.cols
+    }
+
+    fn output_cols(&self) -> usize {
+        self.output.cols
+    }
+
+    fn get(&self, index: usize) -> (Vec<f32>, Vec<f32>) {
+        (
+            self.input.data[index].clone(),
+            self.output.data[index].clone(),
+        )
+    }
+
+    fn batch(&self, start: usize, end: usize) -> (Mat, Mat) {
+        (
+            Mat::slice_rows(&self.input, start, end),
+            Mat::slice_rows(&self.output, start, end),
+        )
+    }
+}
+
+/// Samples computed on demand by a function of their index.
+pub struct Lazy<F> {
+    len: usize,
+    input_cols: usize,
+    output_cols: usize,
+    sample: F,
+}
+
+impl<F: Fn(usize) -> (Vec<f32>, Vec<f32>)> Lazy<F> {
+    pub fn new(len: usize, input_cols: usize, output_cols: usize, sample: F) -> Lazy<F> {
+        Lazy {
+            len,
+            input_cols,
+            output_cols,
+            sample,
+        }
+    }
+}
+
+impl<F: Fn(usize) -> (Vec<f32>, Vec<f32>)> Dataset for Lazy<F> {
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn input_cols(&self) -> usize {
+        self.input_cols
+    }
+
+    fn output_cols(&self) -> usize {
+        self.output_cols
+    }
+
+    fn get(&self, index: usize) -> (Vec<f32>, Vec<f32>) {
+        assert!(index < self.len);
+        (self.sample)(index)
+    }
+}
+
+/// Samples read from a text file as they're needed, one sample per line with
+/// the input values followed by the output values, separated by commas or
+/// whitespace. Only the offset of every line is kept in memory, but every
+/// line is checked when opening so reading samples later can't fail on them.
+pub struct Streamed {
+    reader: Mutex<BufReader<File>>,
+    offsets: Vec<u64>,
+    input_cols: usize,
+    output_cols: usize,
+}
+
+impl Streamed {
+    pub fn open<P: AsRef<Path>>(
+        path: P,
+        input_cols: usize,
+        output_cols: usize,
+    ) -> io::Result<Streamed> {
+        let mut reader = BufReader::new(File::open(path)?);
+
+        let mut offsets = Vec::new();
+        let mut offset = 0;
+        let mut line = String::new();
+        for number in 1.. {
+            line.clear();
+            let read = reader.read_line(&mut line)?;
+            if read == 0 {
+                break;
+            }
+            if !line.trim().is_empty() {
+                parse_sample(&line, input_cols + output_cols).map_err(|msg| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("line {}: {}", number, msg),
+                    )
+                })?;
+                offsets.push(offset);
+            }
+            offset += read as u64;
+        }
+
+        Ok(Streamed {
+            reader: Mutex::new(reader),
+            offsets,
+            input_cols,
+            output_cols,
+        })
+    }
+}
+
+impl Dataset for Streamed {
+    fn len(&self) -> usize {
+        self.offsets.len()
+    }
+
+    fn input_cols(&self) -> usize {
+        self.input_cols
+    }
+
+    fn output_cols(&self) -> usize {
+        self.output_cols
+    }
+
+    fn get(&self, index: usize) -> (Vec<f32>, Vec<f32>) {
+        let mut reader = self.reader.lock().unwrap();
+        let mut line = String::new();
+        reader
+            .seek(SeekFrom::Start(self.offsets[index]))
+            .and_then(|_| reader.read_line(&mut line))
+            .unwrap_or_else(|err| panic!("failed to read sample {}: {}", index, err));
+
+        // Only a file changed since it was opened gets here
+        let mut values = parse_sample(&line, self.input_cols + self.output_cols)
+            .unwrap_or_else(|msg| panic!("sample {}: {}", index, msg));
+        let output = values.split_off(self.input_cols);
+        (values, output)
+    }
+}
+
+// The values on a line of a streamed file, which has to hold `cols` of them
+fn parse_sample(line: &str, cols: usize) -> Result<Vec<f32>, String> {
+    let values = line
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|field| !field.is_empty())
+        .map(|field| {
+            field
+                .parse()
+                .map_err(|_| format!("`{}` is not a number", field))
+        })
+        .collect::<Result<Vec<f32>, _>>()?;
+    if values.len() != cols {
+        return Err(format!("expected {} values, found {}", cols, values.len()));
+    }
+    Ok(values)
+}
@@ -0,0 +1,191 @@
+use std::f32::consts::{PI, TAU};
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use crate::{random::gaussian, Mat};
+
+// Every generator returns `(t_input, t_output)`. The point sets are seeded and
+// keep their inputs roughly within 0..=1 and their outputs within the
+// sigmoid's range, so they can be fed to a network as they are.
+
+/// Every combination of `inputs` bits, 1 when an odd number of them is set.
+pub fn xor(inputs: usize) -> (Mat, Mat) {
+    truth_table(inputs, |bits| bits.iter().filter(|b| **b).count() % 2 == 1)
+}
+
+pub fn and(inputs: usize) -> (Mat, Mat) {
+    truth_table(inputs, |bits| bits.iter().all(|b| *b))
+}
+
+pub fn or(inputs: usize) -> (Mat, Mat) {
+    truth_table(inputs, |bits| bits.iter().any(|b| *b))
+}
+
+pub fn nand(inputs: usize) -> (Mat, Mat) {
+    truth_table(inputs, |bits| !bits.iter().all(|b| *b))
+}
+
+/// Every pair of `bits`-bit numbers and their `bits + 1`-bit sum, all most
+/// significant bit first.
+pub fn adder(bits: usize) -> (Mat, Mat) {
+    arithmetic(bits, bits + 1, |a, b| a + b)
+}
+
+/// Every pair of `bits`-bit numbers and their `2 * bits`-bit product, all
+/// most significant bit first.
+pub fn multiplier(bits: usize) -> (Mat, Mat) {
+    arithmetic(bits, 2 * bits, |a, b| a * b)
+}
+
+/// Two interleaved spirals of `samples` points in total, each winding
+/// `turns` times around the centre. The output is the spiral a point is on.
+pub fn spirals(samples: usize, turns: f32, noise: f32, seed: u64) -> (Mat, Mat) {
+    let mut rng = StdRng::seed_from_u64(seed);
+    points(samples, |i| {
+        let class = i % 2;
+        let t: f32 = rng.gen_range(0.05..1.0);
+        let angle = t * turns * TAU + class as f32 * PI;
+        let x = 0.5 + 0.45 * t * angle.cos() + gaussian(&mut rng) * noise;
+        let y = 0.5 + 0.45 * t * angle.sin() + gaussian(&mut rng) * noise;
+        ([x, y], class)
+    })
+}
+
+/// Two rings around the same centre, the inner one `factor` times the size
+/// of the outer one and labelled 1.
+pub fn circles(samples: usize, factor: f32, noise: f32, seed: u64) -> (Mat, Mat) {
+    let mut rng = StdRng::seed_from_u64(seed);
+    points(samples, |i| {
+        let class = i % 2;
+        let radius = if class == 1 { 0.45 * factor } else { 0.45 };
+        let angle: f32 = rng.gen_range(0.0..TAU);
+        let x = 0.5 + radius * angle.cos() + gaussian(&mut rng) * noise;
+        let y = 0.5 + radius * angle.sin() + gaussian(&mut rng) * noise;
+        ([x, y], class)
+    })
+}
+
+/// Two interlocking half circles, the lower one labelled 1.
+pub fn moons(samples: usize, noise: f32, seed: u64) -> (Mat, Mat) {
+    let mut rng = StdRng::seed_from_u64(seed);
+    points(samples, |i| {
+        let class = i % 2;
+        let angle: f32 = rng.gen_range(0.0..PI);
+        // the usual moons span -1..2 by -0.5..1, shrunk into the unit square
+        let (x, y) = if class == 0 {
+            (angle.cos(), angle.sin())
+        } else {
+            (1.0 - angle.cos(), 0.5 - angle.sin())
+        };
+        let x = (x + 1.0) / 3.0 + gaussian(&mut rng) * noise;
+        let y = (y + 0.5) / 3.0 + 0.25 + gaussian(&mut rng) * noise;
+        ([x, y], class)
+    })
+}
+
+/// Gaussian clusters with a standard deviation of `spread` around `centers`
+/// random points. The output is one-hot over the clusters.
+pub fn blobs(samples: usize, centers: usize, spread: f32, seed: u64) -> (Mat, Mat) {
+    assert!(centers > 0);
+    let mut rng = StdRng::seed_from_u64(seed);
+    let means: Vec<(f32, f32)> = (0..centers)
+        .map(|_| (rng.gen_range(0.2..0.8), rng.gen_range(0.2..0.8)))
+        .collect();
+
+    let (t_input, classes) = points(samples, |i| {
+        let (mx, my) = means[i % centers];
+        let x = mx + gaussian(&mut rng) * spread;
+        let y = my + gaussian(&mut rng) * spread;
+        ([x, y], i % centers)
+    });
+    let t_output = mat(classes
+        .data
+        .iter()
+        .map(|row| {
+            let mut one_hot = vec![0.0; centers];
+            one_hot[row[0] as usize] = 1.0;
+            one_hot
+        })
+        .collect());
+
+    (t_input, t_output)
+}
+
+/// One period of a sine wave squeezed into `0.1..=0.9`, sampled at random
+/// positions with Gaussian noise of standard deviation `noise` on the
+/// output.
+pub fn sine(samples: usize, noise: f32, seed: u64) -> (Mat, Mat) {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut xs: Vec<f32> = (0..samples).map(|_| rng.gen_range(0.0..=1.0)).collect();
+    xs.sort_by(f32::total_cmp);
+
+    let t_output = mat(xs
+        .iter()
+        .map(|x| vec![0.5 + 0.4 * (TAU * x).sin() + gaussian(&mut rng) * noise])
+        .collect());
+    (mat(xs.into_iter().map(|x| vec![x]).collect()), t_output)
+}
+
+/// `samples` evenly spaced inputs from 1 down to 0, each mapped to its
+/// opposite `1 - x`.
+pub fn opposite(samples: usize) -> (Mat, Mat) {
+    assert!(samples > 1);
+    let xs: Vec<f32> = (0..samples)
+        .map(|i| 1.0 - i as f32 / (samples - 1) as f32)
+        .collect();
+    (
+        mat(xs.iter().map(|x| vec![*x]).collect()),
+        mat(xs.iter().map(|x| vec![1.0 - x]).collect()),
+    )
+}
+
+fn truth_table(inputs: usize, f: impl Fn(&[bool]) -> bool) -> (Mat, Mat) {
+    assert!(inputs > 0);
+    let (t_input, t_output) = (0..1 << inputs)
+        .map(|n| {
+            let row = to_bits(n, inputs);
+            let bits: Vec<bool> = row.iter().map(|b| *b == 1.0).collect();
+            (row, vec![f(&bits) as i32 as f32])
+        })
+        .unzip();
+    (mat(t_input), mat(t_output))
+}
+
+fn arithmetic(bits: usize, out_bits: usize, f: impl Fn(usize, usize) -> usize) -> (Mat, Mat) {
+    assert!(bits > 0);
+    let mut t_input = Vec::new();
+    let mut t_output = Vec::new();
+    for a in 0..1 << bits {
+        for b in 0..1 << bits {
+            let mut row = to_bits(a, bits);
+            row.extend(to_bits(b, bits));
+            t_input.push(row);
+            t_output.push(to_bits(f(a, b), out_bits));
+        }
+    }
+    (mat(t_input), mat(t_output))
+}
+
+// `count` labelled 2d points, returned as the points and a column of labels
+fn points(count: usize, mut sample: impl FnMut(usize) -> ([f32; 2], usize)) -> (Mat, Mat) {
+    let (t_input, t_output) = (0..count)
+        .map(|i| {
+            let (point, class) = sample(i);
+            (point.to_vec(), vec![class as f32])
+        })
+        .unzip();
+    (mat(t_input), mat(t_output))
+}
+
+// Most significant bit first
+fn to_bits(n: usize, bits: usize) -> Vec<f32> {
+    (0..bits).rev().map(|i| ((n >> i) & 1) as f32).collect()
+}
+
+fn mat(data: Vec<Vec<f32>>) -> Mat {
+    Mat {
+        rows: data.len(),
+        cols: data.first().map_or(0, |row| row.len()),
+        data,
+    }
+}
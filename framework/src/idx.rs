@@ -0,0 +1,233 @@
+use std::{
+    fmt,
+    fs::File,
+    io::{self, BufReader, Read},
+    path::Path,
+};
+
+use crate::Mat;
+
+/// Element type of an IDX file, the third byte of its magic number.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IdxType {
+    U8,
+    I8,
+    I16,
+    I32,
+    F32,
+    F64,
+}
+
+impl IdxType {
+    fn from_code(code: u8) -> Option<IdxType> {
+        match code {
+            0x08 => Some(IdxType::U8),
+            0x09 => Some(IdxType::I8),
+            0x0B => Some(IdxType::I16),
+            0x0C => Some(IdxType::I32),
+            0x0D => Some(IdxType::F32),
+            0x0E => Some(IdxType::F64),
+            _ => None,
+        }
+    }
+
+    fn size(self) -> usize {
+        match self {
+            IdxType::U8 | IdxType::I8 => 1,
+            IdxType::I16 => 2,
+            IdxType::I32 | IdxType::F32 => 4,
+            IdxType::F64 => 8,
+        }
+    }
+}
+
+/// The contents of an IDX file, the format the MNIST and Fashion-MNIST sets
+/// ship in. Files have to be uncompressed first.
+#[derive(Clone, Debug)]
+pub struct Idx {
+    pub kind: IdxType,
+    /// Size of every dimension, the first one counting the items.
+    pub dims: Vec<usize>,
+    pub data: Vec<f32>,
+}
+
+#[derive(Debug)]
+pub enum IdxError {
+    Io(io::Error),
+    BadMagic([u8; 4]),
+    /// The file ended before all the values its dimensions promise.
+    Truncated,
+    /// Dimensions whose product doesn't fit in memory.
+    TooLarge,
+    /// Images and labels files with a different number of items.
+    CountMismatch {
+        images: usize,
+        labels: usize,
+    },
+    /// A label that doesn't fit the requested number of classes.
+    BadLabel(f32),
+    /// Dimensions that can't be read as images or labels.
+    BadDims(Vec<usize>),
+}
+
+impl fmt::Display for IdxError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IdxError::Io(err) => write!(f, "{}", err),
+            IdxError::BadMagic(magic) => write!(f, "not an IDX file, magic number {:02x?}", magic),
+            IdxError::Truncated => write!(f, "file is shorter than its header says"),
+            IdxError::TooLarge => write!(f, "dimensions are too large"),
+            IdxError::CountMismatch { images, labels } => {
+                write!(f, "{} images but {} labels", images, labels)
+            }
+            IdxError::BadLabel(label) => write!(f, "label {} is out of range", label),
+            IdxError::BadDims(dims) => write!(f, "unexpected dimensions {:?}", dims),
+        }
+    }
+}
+
+impl std::error::Error for IdxError {}
+
+impl From<io::Error> for IdxError {
+    fn from(err: io::Error) -> IdxError {
+        if err.kind() == io::ErrorKind::UnexpectedEof {
+            IdxError::Truncated
+        } else {
+            IdxError::Io(err)
+        }
+    }
+}
+
+impl Idx {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Idx, IdxError> {
+        Self::read(&mut BufReader::new(File::open(path)?))
+    }
+
+    pub fn read<R: Read>(r: &mut R) -> Result<Idx, IdxError> {
+        let mut magic = [0; 4];
+        r.read_exact(&mut magic)?;
+        let kind = match IdxType::from_code(magic[2]) {
+            Some(kind) if magic[0] == 0 && magic[1] == 0 && magic[3] > 0 => kind,
+            _ => return Err(IdxError::BadMagic(magic)),
+        };
+
+        let mut dims = Vec::with_capacity(magic[3] as usize);
+        for _ in 0..magic[3] {
+            let mut dim = [0; 4];
+            r.read_exact(&mut dim)?;
+            dims.push(u32::from_be_bytes(dim) as usize);
+        }
+
+        let size = dims
+            .iter()
+            .try_fold(kind.size(), |size, &dim| size.checked_mul(dim))
+            .ok_or(IdxError::TooLarge)?;
+        // The header can't be trusted with the allocation, so the buffer only
+        // grows as far as the file really goes
+        let mut bytes = Vec::new();
+        r.take(size as u64).read_to_end(&mut bytes)?;
+        if bytes.len() < size {
+            return Err(IdxError::Truncated);
+        }
+
+        let data = bytes
+            .chunks_exact(kind.size())
+            .map(|b| match kind {
+                IdxType::U8 => b[0] as f32,
+                IdxType::I8 => b[0] as i8 as f32,
+                IdxType::I16 => i16::from_be_bytes([b[0], b[1]]) as f32,
+                IdxType::I32 => i32::from_be_bytes([b[0], b[1], b[2], b[3]]) as f32,
+                IdxType::F32 => f32::from_be_bytes([b[0], b[1], b[2], b[3]]),
+                IdxType::F64 => {
+                    f64::from_be_bytes([b[0], b[1], b[2], b[3], b[4], b[5], b[6], b[7]]) as f32
+                }
+            })
+            .collect();
+
+        Ok(Idx { kind, dims, data })
+    }
+
+    /// Number of items, the size of the first dimension.
+    pub fn items(&self) -> usize {
+        self.dims[0]
+    }
+
+    /// Width, height and channels of the items of an images file, whose
+    /// dimensions are items, rows, columns and optionally channels.
+    pub fn image_dims(&self) -> Option<(usize, usize, usize)> {
+        match self.dims[..] {
+            [_, height, width] => Some((width, height, 1)),
+            [_, height, width, channels] => Some((width, height, channels)),
+            _ => None,
+        }
+    }
+
+    /// One row per item with every value scaled into `0..=1`: divided by 255
+    /// for bytes, by the observed range for other types.
+    pub fn to_inputs(&self) -> Result<Mat, IdxError> {
+        // items without any values would make rows without columns
+        if self.dims[1..].contains(&0) {
+            return Err(IdxError::BadDims(self.dims.clone()));
+        }
+
+        let (min, max) = match self.kind {
+            IdxType::U8 => (0.0, 255.0),
+            _ => self
+                .data
+                .iter()
+                .fold((f32::INFINITY, f32::NEG_INFINITY), |(lo, hi), &v| {
+                    (lo.min(v), hi.max(v))
+                }),
+        };
+        let range = if max > min { max - min } else { 1.0 };
+
+        let cols = self.dims[1..].iter().product();
+        Ok(Mat {
+            rows: self.items(),
+            cols,
+            data: self
+                .data
+                .chunks(cols)
+                .map(|item| item.iter().map(|v| (v - min) / range).collect())
+                .collect(),
+        })
+    }
+
+    /// One-hot rows for a labels file with one value per item.
+    pub fn to_one_hot(&self, classes: usize) -> Result<Mat, IdxError> {
+        if self.dims.len() != 1 {
+            return Err(IdxError::BadDims(self.dims.clone()));
+        }
+
+        let mut data = vec![vec![0.0; classes]; self.items()];
+        for (row, &label) in data.iter_mut().zip(&self.data) {
+            if label < 0.0 || label as usize >= classes || label.fract() != 0.0 {
+                return Err(IdxError::BadLabel(label));
+            }
+            row[label as usize] = 1.0;
+        }
+
+        Ok(Mat {
+            rows: self.items(),
+            cols: classes,
+            data,
+        })
+    }
+}
+
+/// Loads an MNIST style images/labels pair as normalized pixel rows and
+/// one-hot digit rows.
+pub fn load_mnist<P: AsRef<Path>>(images: P, labels: P) -> Result<(Mat, Mat), IdxError> {
+    const DIGITS: usize = 10;
+
+    let images = Idx::load(images)?;
+    let labels = Idx::load(labels)?;
+    if images.items() != labels.items() {
+        return Err(IdxError::CountMismatch {
+            images: images.items(),
+            labels: labels.items(),
+        });
+    }
+
+    Ok((images.to_inputs()?, labels.to_one_hot(DIGITS)?))
+}
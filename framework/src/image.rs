@@ -0,0 +1,431 @@
+use std::{
+    fmt, fs,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+use crate::{Mat, NN};
+
+/// A grayscale (1 channel) or RGB (3 channels) image with every channel
+/// value in `0..=1`, stored row by row with the channels interleaved.
+#[derive(Clone, Debug)]
+pub struct Image {
+    pub width: usize,
+    pub height: usize,
+    pub channels: usize,
+    pub pixels: Vec<f32>,
+}
+
+#[derive(Debug)]
+pub enum ImageError {
+    Io(io::Error),
+    Png(String),
+    /// A malformed PGM/PPM file.
+    Pnm(String),
+    /// Neither a PGM/PPM nor a PNG file, or a file name without one of their
+    /// extensions when saving.
+    Unsupported,
+    /// A channel count other than 1 or 3.
+    Channels(usize),
+}
+
+impl fmt::Display for ImageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImageError::Io(err) => write!(f, "{}", err),
+            ImageError::Png(msg) => write!(f, "png: {}", msg),
+            ImageError::Pnm(msg) => write!(f, "pnm: {}", msg),
+            ImageError::Unsupported => write!(f, "unsupported image format"),
+            ImageError::Channels(channels) => {
+                write!(f, "{} channels, images have 1 or 3", channels)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ImageError {}
+
+impl From<io::Error> for ImageError {
+    fn from(err: io::Error) -> ImageError {
+        ImageError::Io(err)
+    }
+}
+
+impl From<png::DecodingError> for ImageError {
+    fn from(err: png::DecodingError) -> ImageError {
+        ImageError::Png(err.to_string())
+    }
+}
+
+impl From<png::EncodingError> for ImageError {
+    fn from(err: png::EncodingError) -> ImageError {
+        ImageError::Png(err.to_string())
+    }
+}
+
+const PNG_SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1A, b'\n'];
+
+impl Image {
+    pub fn new(width: usize, height: usize, channels: usize) -> Result<Image, ImageError> {
+        if channels != 1 && channels != 3 {
+            return Err(ImageError::Channels(channels));
+        }
+        Ok(Image {
+            width,
+            height,
+            channels,
+            pixels: vec![0.0; width * height * channels],
+        })
+    }
+
+    /// Loads a PGM/PPM (P2, P3, P5, P6) or PNG file, telling them apart by
+    /// their contents. Alpha channels are dropped.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Image, ImageError> {
+        Self::decode(&fs::read(path)?)
+    }
+
+    pub fn decode(bytes: &[u8]) -> Result<Image, ImageError> {
+        if bytes.starts_with(&PNG_SIGNATURE) {
+            Self::decode_png(bytes)
+        } else if bytes.first() == Some(&b'P') {
+            Self::decode_pnm(bytes)
+        } else {
+            Err(ImageError::Unsupported)
+        }
+    }
+
+    fn decode_png(bytes: &[u8]) -> Result<Image, ImageError> {
+        let mut decoder = png::Decoder::new(bytes);
+        // palettes expanded, 16 bit channels cut down to 8
+        decoder.set_transformations(png::Transformations::normalize_to_color8());
+        let mut reader = decoder.read_info()?;
+        let mut buf = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut buf)?;
+
+        let (stride, channels) = match info.color_type {
+            png::ColorType::Grayscale => (1, 1),
+            png::ColorType::GrayscaleAlpha => (2, 1),
+            png::ColorType::Rgb => (3, 3),
+            png::ColorType::Rgba => (4, 3),
+            png::ColorType::Indexed => return Err(ImageError::Png("unexpanded palette".into())),
+        };
+
+        let (width, height) = (info.width as usize, info.height as usize);
+        let mut image = Image::new(width, height, channels)?;
+        for y in 0..height {
+            let line = &buf[y * info.line_size..];
+            for x in 0..width {
+                for c in 0..channels {
+                    image.pixels[(y * width + x) * channels + c] =
+                        line[x * stride + c] as f32 / 255.0;
+                }
+            }
+        }
+
+        Ok(image)
+    }
+
+    fn decode_pnm(bytes: &[u8]) -> Result<Image, ImageError> {
+        let bad = |msg: &str| ImageError::Pnm(msg.to_owned());
+
+        let mut pos = 0;
+        let mut header = Vec::new();
+        while header.len() < 4 {
+            header.push(pnm_token(bytes, &mut pos).ok_or_else(|| bad("truncated header"))?);
+        }
+
+        let (channels, binary) = match header[0].as_str() {
+            "P2" => (1, false),
+            "P3" => (3, false),
+            "P5" => (1, true),
+            "P6" => (3, true),
+            _ => return Err(bad("only P2, P3, P5 and P6 are supported")),
+        };
+        let parse = |token: &str| token.parse::<usize>().map_err(|_| bad("bad number"));
+        let width = parse(&header[1])?;
+        let height = parse(&header[2])?;
+        let maxval = parse(&header[3])?;
+        if maxval == 0 || maxval > 65535 {
+            return Err(bad("max value out of range"));
+        }
+
+        // Every value takes at least a byte, so a header promising more than
+        // the file holds is caught before allocating for it
+        let size = if binary && maxval >= 256 { 2 } else { 1 };
+        let count = width
+            .checked_mul(height)
+            .and_then(|pixels| pixels.checked_mul(channels * size))
+            .ok_or_else(|| bad("dimensions too large"))?;
+        if count > bytes.len() - pos {
+            return Err(bad("truncated pixel data"));
+        }
+
+        let mut image = Image::new(width, height, channels)?;
+        if binary {
+            // a single whitespace byte separates the header from the pixels
+            let data = &bytes[(pos + 1).min(bytes.len())..];
+            if data.len() < count {
+                return Err(bad("truncated pixel data"));
+            }
+            for (i, pixel) in image.pixels.iter_mut().enumerate() {
+                let val = if size == 1 {
+                    data[i] as usize
+                } else {
+                    u16::from_be_bytes([data[2 * i], data[2 * i + 1]]) as usize
+                };
+                if val > maxval {
+                    return Err(bad("value above the max value"));
+                }
+                *pixel = val as f32 / maxval as f32;
+            }
+        } else {
+            for pixel in image.pixels.iter_mut() {
+                let token =
+                    pnm_token(bytes, &mut pos).ok_or_else(|| bad("truncated pixel data"))?;
+                let val = parse(&token)?;
+                if val > maxval {
+                    return Err(bad("value above the max value"));
+                }
+                *pixel = val as f32 / maxval as f32;
+            }
+        }
+
+        Ok(image)
+    }
+
+    /// Channel values of the pixel at `x`, `y`.
+    pub fn get(&self, x: usize, y: usize) -> &[f32] {
+        let i = (y * self.width + x) * self.channels;
+        &self.pixels[i..i + self.channels]
+    }
+
+    /// Averages the channels of an RGB image into one.
+    pub fn to_grayscale(&self) -> Image {
+        if self.channels == 1 {
+            return self.clone();
+        }
+        Image {
+            width: self.width,
+            height: self.height,
+            channels: 1,
+            pixels: self
+                .pixels
+                .chunks(self.channels)
+                .map(|px| px.iter().sum::<f32>() / self.channels as f32)
+                .collect(),
+        }
+    }
+
+    /// Repeats the channel of a grayscale image for red, green and blue.
+    pub fn to_rgb(&self) -> Image {
+        if self.channels == 3 {
+            return self.clone();
+        }
+        Image {
+            width: self.width,
+            height: self.height,
+            channels: 3,
+            pixels: self.pixels.iter().flat_map(|v| [*v; 3]).collect(),
+        }
+    }
+
+    /// One training row per pixel mapping its position, scaled into `0..=1`,
+    /// to its brightness or RGB values.
+    pub fn to_training(&self) -> (Mat, Mat) {
+        let mut t_input = Mat {
+            rows: self.width * self.height,
+            cols: 2,
+            data: Vec::with_capacity(self.width * self.height),
+        };
+        let mut t_output = Mat {
+            rows: self.width * self.height,
+            cols: self.channels,
+            data: Vec::with_capacity(self.width * self.height),
+        };
+
+        for y in 0..self.height {
+            for x in 0..self.width {
+                t_input
+                    .data
+                    .push(vec![normalized(x, self.width), normalized(y, self.height)]);
+                t_output.data.push(self.get(x, y).to_vec());
+            }
+        }
+
+        (t_input, t_output)
+    }
+
+    /// Samples a network trained on [`Image::to_training`] rows at any
+    /// resolution. Its output width decides the number of channels, which
+    /// has to be 1 or 3.
+    pub fn from_nn(nn: &NN, width: usize, height: usize) -> Result<Image, ImageError> {
+        Self::from_nn_blended(nn, width, height, &[])
+    }
+
+    /// Like [`Image::from_nn`] for networks taking `extra` inputs after the
+    /// pixel position, such as the blend input of [`interpolation_training`].
+    pub fn from_nn_blended(
+        nn: &NN,
+        width: usize,
+        height: usize,
+        extra: &[f32],
+    ) -> Result<Image, ImageError> {
+        let mut nn = nn.clone();
+        assert_eq!(nn.activations[0].cols, 2 + extra.len());
+        let channels = nn.activations[nn.count - 1].cols;
+
+        let mut image = Image::new(width, height, channels)?;
+        nn.activations[0].data[0][2..].copy_from_slice(extra);
+        for y in 0..height {
+            for x in 0..width {
+                nn.activations[0].data[0][0] = normalized(x, width);
+                nn.activations[0].data[0][1] = normalized(y, height);
+                NN::forward(&mut nn);
+
+                let i = (y * width + x) * channels;
+                image.pixels[i..i + channels]
+                    .copy_from_slice(&nn.activations[nn.count - 1].data[0]);
+            }
+        }
+
+        Ok(image)
+    }
+
+    /// RGBA bytes, the layout textures are usually created from.
+    pub fn to_rgba8(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.width * self.height * 4);
+        for px in self.pixels.chunks(self.channels) {
+            if self.channels == 1 {
+                bytes.extend_from_slice(&[to_byte(px[0]); 3]);
+            } else {
+                bytes.extend(px.iter().map(|v| to_byte(*v)));
+            }
+            bytes.push(255);
+        }
+        bytes
+    }
+
+    /// Saves as PGM/PPM or PNG depending on the extension of `path`. A `.pnm`
+    /// extension picks whichever of PGM and PPM fits the channels.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), ImageError> {
+        let extension = path
+            .as_ref()
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(|ext| ext.to_ascii_lowercase());
+
+        // Checked before creating the file so nothing is left behind
+        let format = match extension.as_deref() {
+            Some(format @ ("png" | "pgm" | "ppm" | "pnm")) => format,
+            _ => return Err(ImageError::Unsupported),
+        };
+        let mut w = BufWriter::new(fs::File::create(&path)?);
+        match format {
+            "png" => self.write_png(&mut w)?,
+            "pgm" => self.to_grayscale().write_pnm(&mut w)?,
+            "ppm" => self.to_rgb().write_pnm(&mut w)?,
+            _ => self.write_pnm(&mut w)?,
+        }
+        w.flush()?;
+
+        Ok(())
+    }
+
+    /// Binary PGM for grayscale, PPM for RGB.
+    pub fn write_pnm<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        let magic = if self.channels == 1 { "P5" } else { "P6" };
+        write!(w, "{}\n{} {}\n255\n", magic, self.width, self.height)?;
+        let bytes: Vec<u8> = self.pixels.iter().map(|v| to_byte(*v)).collect();
+        w.write_all(&bytes)
+    }
+
+    pub fn write_png<W: Write>(&self, w: &mut W) -> Result<(), ImageError> {
+        let mut encoder = png::Encoder::new(w, self.width as u32, self.height as u32);
+        encoder.set_color(if self.channels == 1 {
+            png::ColorType::Grayscale
+        } else {
+            png::ColorType::Rgb
+        });
+        encoder.set_depth(png::BitDepth::Eight);
+
+        let bytes: Vec<u8> = self.pixels.iter().map(|v| to_byte(*v)).collect();
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(&bytes)?;
+
+        Ok(())
+    }
+}
+
+/// Training rows for several images at once: every pixel maps its position
+/// and a blend input to its values, the blend input going from 0 for the
+/// first image to 1 for the last. Sampling a trained network at blend values
+/// in between morphs from one image to the next.
+pub fn interpolation_training(images: &[Image]) -> (Mat, Mat) {
+    assert!(!images.is_empty());
+    let channels = images[0].channels;
+    assert!(images.iter().all(|image| image.channels == channels));
+
+    let rows = images.iter().map(|image| image.width * image.height).sum();
+    let mut t_input = Mat {
+        rows,
+        cols: 3,
+        data: Vec::with_capacity(rows),
+    };
+    let mut t_output = Mat {
+        rows,
+        cols: channels,
+        data: Vec::with_capacity(rows),
+    };
+
+    for (i, image) in images.iter().enumerate() {
+        let blend = normalized(i, images.len());
+        let (input, output) = image.to_training();
+        t_input.data.extend(input.data.into_iter().map(|mut row| {
+            row.push(blend);
+            row
+        }));
+        t_output.data.extend(output.data);
+    }
+
+    (t_input, t_output)
+}
+
+// 0 at the first pixel and 1 at the last
+fn normalized(i: usize, size: usize) -> f32 {
+    if size > 1 {
+        i as f32 / (size - 1) as f32
+    } else {
+        0.0
+    }
+}
+
+fn to_byte(val: f32) -> u8 {
+    (val.clamp(0.0, 1.0) * 255.0).round() as u8
+}
+
+// Next whitespace separated token of a PNM header, skipping `#` comments.
+// Leaves `pos` on the byte right after the token.
+fn pnm_token(bytes: &[u8], pos: &mut usize) -> Option<String> {
+    loop {
+        while *pos < bytes.len() && bytes[*pos].is_ascii_whitespace() {
+            *pos += 1;
+        }
+        if *pos < bytes.len() && bytes[*pos] == b'#' {
+            while *pos < bytes.len() && bytes[*pos] != b'\n' {
+                *pos += 1;
+            }
+        } else {
+            break;
+        }
+    }
+
+    let start = *pos;
+    while *pos < bytes.len() && !bytes[*pos].is_ascii_whitespace() {
+        *pos += 1;
+    }
+    if start == *pos {
+        return None;
+    }
+
+    String::from_utf8(bytes[start..*pos].to_vec()).ok()
+}
@@ -1,4 +1,22 @@
-use rand::Rng;
+use loss::{Loss, Mse};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+pub mod augment;
+pub mod config;
+pub mod csv;
+pub mod dataset;
+pub mod generate;
+pub mod idx;
+pub mod image;
+pub mod loss;
+pub mod metrics;
+pub mod model;
+pub mod optimizer;
+pub mod preprocess;
+mod random;
+pub mod schedule;
+pub mod split;
+pub mod trainer;
 
 #[macro_export]
 macro_rules! nn_input {
@@ -48,6 +66,11 @@ impl NN {
     }
 
     pub fn cost(nn: &NN, t_input: &Mat, t_output: &Mat) -> f32 {
+        Self::loss_cost(nn, t_input, t_output, &Mse)
+    }
+
+    // cost summed over every output of every row
+    pub fn loss_cost(nn: &NN, t_input: &Mat, t_output: &Mat, loss: &dyn Loss) -> f32 {
         let mut nn = nn.clone();
         assert_eq!(t_input.rows, t_output.rows);
         assert_eq!(t_output.cols, nn.activations[nn.count - 1].cols);
@@ -63,15 +86,32 @@ impl NN {
             Self::forward(&mut nn);
             let q = t_output.cols;
             for j in 0..q {
-                let diff: f32 = nn_output!(nn).data[0][j] - y.data[0][j];
-                // cost is magnified
-                cost += diff * diff;
+                cost += loss.cost(nn_output!(nn).data[0][j], y.data[0][j]);
             }
         }
 
         cost
     }
 
+    // one output row per input row
+    pub fn predict(nn: &NN, input: &Mat) -> Mat {
+        let mut nn = nn.clone();
+        assert_eq!(input.cols, nn_input!(nn).cols);
+
+        let mut output = Mat {
+            rows: input.rows,
+            cols: nn_output!(nn).cols,
+            data: Vec::with_capacity(input.rows),
+        };
+        for i in 0..input.rows {
+            Mat::copy(&mut nn_input!(nn), &Mat::row(input, i));
+            Self::forward(&mut nn);
+            output.data.push(nn_output!(nn).data[0].clone());
+        }
+
+        output
+    }
+
     pub fn learn(nn: &mut NN, g: &NN, rate: f32) {
         for i in 0..nn.count - 1 {
             for j in 0..nn.weights[i].rows {
@@ -89,16 +129,25 @@ impl NN {
     }
 
     pub fn randomize(nn: &mut NN, min: f32, max: f32) {
+        Self::randomize_rng(nn, min, max, &mut rand::thread_rng());
+    }
+
+    // same as randomize but repeatable
+    pub fn randomize_seeded(nn: &mut NN, min: f32, max: f32, seed: u64) {
+        Self::randomize_rng(nn, min, max, &mut StdRng::seed_from_u64(seed));
+    }
+
+    fn randomize_rng<R: Rng>(nn: &mut NN, min: f32, max: f32, rng: &mut R) {
         for i in 0..nn.count - 1 {
             for j in 0..nn.weights[i].rows {
                 for k in 0..nn.weights[i].cols {
-                    nn.weights[i].data[j][k] = rand_float(min, max);
+                    nn.weights[i].data[j][k] = rng.gen_range(min..max);
                 }
             }
 
             for j in 0..nn.biases[i].rows {
                 for k in 0..nn.biases[i].cols {
-                    nn.biases[i].data[j][k] = rand_float(min, max);
+                    nn.biases[i].data[j][k] = rng.gen_range(min..max);
                 }
             }
         }
@@ -148,6 +197,10 @@ impl NN {
     }
 
     pub fn backprop(nn: &mut NN, g: &mut NN, t_input: &Mat, t_output: &Mat) {
+        Self::loss_backprop(nn, g, t_input, t_output, &Mse);
+    }
+
+    pub fn loss_backprop(nn: &mut NN, g: &mut NN, t_input: &Mat, t_output: &Mat, loss: &dyn Loss) {
         assert_eq!(t_input.rows, t_output.rows);
         let n = t_input.rows;
         assert_eq!(nn.activations[nn.count - 1].cols, t_output.cols);
@@ -164,7 +217,7 @@ impl NN {
 
             for j in 0..t_output.cols {
                 g.activations[nn.count - 1].data[0][j] =
-                    (nn_output!(nn).data[0][j] - t_output.data[i][j]) * 2.0 / n as f32;
+                    loss.gradient(nn_output!(nn).data[0][j], t_output.data[i][j]) / n as f32;
             }
 
             for l in (0..nn.count - 1).rev() {
@@ -306,6 +359,24 @@ impl Mat {
         }
     }
 
+    // rows start..end as a new matrix
+    pub fn slice_rows(mat: &Mat, start: usize, end: usize) -> Mat {
+        assert!(start <= end && end <= mat.rows);
+        Mat {
+            rows: end - start,
+            cols: mat.cols,
+            data: mat.data[start..end].to_vec(),
+        }
+    }
+
+    pub fn select_rows(mat: &Mat, indices: &[usize]) -> Mat {
+        Mat {
+            rows: indices.len(),
+            cols: mat.cols,
+            data: indices.iter().map(|&i| mat.data[i].clone()).collect(),
+        }
+    }
+
     pub fn copy(dst: &mut Mat, src: &Mat) {
         assert_eq!(dst.rows, src.rows);
         assert_eq!(dst.cols, src.cols);
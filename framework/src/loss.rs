@@ -0,0 +1,56 @@
+/// Measures how far the network's outputs are from the targets.
+///
+/// Both methods work on a single output value, the [`NN`](crate::NN) adds up
+/// the costs and averages the gradients over the rows itself.
+pub trait Loss {
+    fn cost(&self, output: f32, target: f32) -> f32;
+
+    /// Derivative of [`Loss::cost`] with respect to `output`.
+    fn gradient(&self, output: f32, target: f32) -> f32;
+}
+
+impl<L: Loss + ?Sized> Loss for Box<L> {
+    fn cost(&self, output: f32, target: f32) -> f32 {
+        (**self).cost(output, target)
+    }
+
+    fn gradient(&self, output: f32, target: f32) -> f32 {
+        (**self).gradient(output, target)
+    }
+}
+
+/// The squared error every network has been trained on so far.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Mse;
+
+impl Loss for Mse {
+    fn cost(&self, output: f32, target: f32) -> f32 {
+        let diff = output - target;
+        diff * diff
+    }
+
+    fn gradient(&self, output: f32, target: f32) -> f32 {
+        (output - target) * 2.0
+    }
+}
+
+/// Binary cross-entropy, for sigmoid outputs read as probabilities.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CrossEntropy;
+
+impl CrossEntropy {
+    // keeps the logs finite when an output saturates
+    const EPS: f32 = 1e-7;
+}
+
+impl Loss for CrossEntropy {
+    fn cost(&self, output: f32, target: f32) -> f32 {
+        let p = output.clamp(Self::EPS, 1.0 - Self::EPS);
+        -(target * p.ln() + (1.0 - target) * (1.0 - p).ln())
+    }
+
+    fn gradient(&self, output: f32, target: f32) -> f32 {
+        let p = output.clamp(Self::EPS, 1.0 - Self::EPS);
+        (p - target) / (p * (1.0 - p))
+    }
+}
@@ -0,0 +1,387 @@
+use std::{
+    fmt,
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+use crate::{split::class_of, Mat, NN};
+
+// keeps log-loss finite when the network is confidently wrong
+const PROB_EPS: f32 = 1e-7;
+
+/// Evaluation of a classifier over labeled rows.
+///
+/// A single output column is treated as a binary classifier thresholded at
+/// 0.5, several columns as one-hot classes where the largest output wins.
+#[derive(Clone, Debug)]
+pub struct Classification {
+    pub classes: usize,
+    /// `confusion[actual][predicted]` row counts.
+    pub confusion: Vec<Vec<usize>>,
+    /// Area under the ROC curve, averaged one-vs-rest over the classes for
+    /// multi-class outputs. `None` when only one class shows up in the labels.
+    pub roc_auc: Option<f32>,
+    pub log_loss: f32,
+}
+
+impl Classification {
+    pub fn evaluate(nn: &NN, input: &Mat, labels: &Mat) -> Classification {
+        assert_eq!(input.rows, labels.rows);
+        let predictions = NN::predict(nn, input);
+        assert_eq!(predictions.cols, labels.cols);
+
+        let binary = labels.cols == 1;
+        let classes = if binary { 2 } else { labels.cols };
+
+        let mut confusion = vec![vec![0; classes]; classes];
+        let mut log_loss = 0.0;
+        for (label, prediction) in labels.data.iter().zip(&predictions.data) {
+            let actual = class_of(label);
+            confusion[actual][class_of(prediction)] += 1;
+
+            let p = if binary {
+                if actual == 1 {
+                    prediction[0]
+                } else {
+                    1.0 - prediction[0]
+                }
+            } else {
+                // sigmoid outputs don't sum to one, so normalize them first
+                let total: f32 = prediction.iter().sum();
+                prediction[actual] / total.max(PROB_EPS)
+            };
+            log_loss -= p.clamp(PROB_EPS, 1.0 - PROB_EPS).ln();
+        }
+        if labels.rows > 0 {
+            log_loss /= labels.rows as f32;
+        }
+
+        let roc_auc = if binary {
+            roc_auc(labels, &predictions, 0)
+        } else {
+            let aucs: Vec<f32> = (0..classes)
+                .filter_map(|class| roc_auc(labels, &predictions, class))
+                .collect();
+            if aucs.is_empty() {
+                None
+            } else {
+                Some(aucs.iter().sum::<f32>() / aucs.len() as f32)
+            }
+        };
+
+        Classification {
+            classes,
+            confusion,
+            roc_auc,
+            log_loss,
+        }
+    }
+
+    pub fn total(&self) -> usize {
+        self.confusion.iter().flatten().sum()
+    }
+
+    fn correct(&self) -> usize {
+        (0..self.classes).map(|i| self.confusion[i][i]).sum()
+    }
+
+    /// Rows whose actual class is `class`.
+    pub fn support(&self, class: usize) -> usize {
+        self.confusion[class].iter().sum()
+    }
+
+    fn predicted(&self, class: usize) -> usize {
+        self.confusion.iter().map(|row| row[class]).sum()
+    }
+
+    pub fn accuracy(&self) -> f32 {
+        ratio(self.correct(), self.total())
+    }
+
+    pub fn precision(&self, class: usize) -> f32 {
+        ratio(self.confusion[class][class], self.predicted(class))
+    }
+
+    pub fn recall(&self, class: usize) -> f32 {
+        ratio(self.confusion[class][class], self.support(class))
+    }
+
+    pub fn f1(&self, class: usize) -> f32 {
+        harmonic_mean(self.precision(class), self.recall(class))
+    }
+
+    /// Unweighted mean of the per-class F1 scores.
+    pub fn macro_f1(&self) -> f32 {
+        (0..self.classes).map(|class| self.f1(class)).sum::<f32>() / self.classes as f32
+    }
+
+    /// F1 over the pooled counts of every class. With exactly one label per
+    /// row this is the same as the accuracy.
+    pub fn micro_f1(&self) -> f32 {
+        // pooled precision and recall are both correct / total
+        self.accuracy()
+    }
+}
+
+impl fmt::Display for Classification {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{:>8} {:>9} {:>9} {:>9} {:>9}",
+            "class", "precision", "recall", "f1", "support"
+        )?;
+        for class in 0..self.classes {
+            writeln!(
+                f,
+                "{:>8} {:>9.4} {:>9.4} {:>9.4} {:>9}",
+                class,
+                self.precision(class),
+                self.recall(class),
+                self.f1(class),
+                self.support(class)
+            )?;
+        }
+        writeln!(f)?;
+        writeln!(f, "{:>8} {:>9.4}", "accuracy", self.accuracy())?;
+        writeln!(f, "{:>8} {:>9.4}", "macro f1", self.macro_f1())?;
+        writeln!(f, "{:>8} {:>9.4}", "micro f1", self.micro_f1())?;
+        match self.roc_auc {
+            Some(auc) => writeln!(f, "{:>8} {:>9.4}", "roc auc", auc)?,
+            None => writeln!(f, "{:>8} {:>9}", "roc auc", "-")?,
+        }
+        writeln!(f, "{:>8} {:>9.4}", "log loss", self.log_loss)?;
+
+        writeln!(f)?;
+        write!(f, "{:>8}", "actual")?;
+        for class in 0..self.classes {
+            write!(f, " {:>6}", format!("p{}", class))?;
+        }
+        writeln!(f)?;
+        for (class, row) in self.confusion.iter().enumerate() {
+            write!(f, "{:>8}", class)?;
+            for count in row {
+                write!(f, " {:>6}", count)?;
+            }
+            writeln!(f)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Evaluation of a regressor. With several output columns every output value
+/// counts as one observation.
+#[derive(Clone, Debug)]
+pub struct Regression {
+    /// Coefficient of determination, 1 is a perfect fit and 0 is no better
+    /// than predicting each column's mean.
+    pub r2: f32,
+    pub rmse: f32,
+    pub mae: f32,
+    /// Mean absolute percentage error in percent. Targets equal to zero are
+    /// left out since their percentage error is undefined.
+    pub mape: f32,
+    pub max_error: f32,
+}
+
+impl Regression {
+    pub fn evaluate(nn: &NN, input: &Mat, targets: &Mat) -> Regression {
+        Self::from_residuals(&Residuals::new(nn, input, targets))
+    }
+
+    pub fn from_residuals(residuals: &Residuals) -> Regression {
+        let cols = residuals.rows.first().map_or(0, |row| row.target.len());
+        let mut means = vec![0.0; cols];
+        for row in &residuals.rows {
+            for (mean, target) in means.iter_mut().zip(&row.target) {
+                *mean += target / residuals.rows.len() as f32;
+            }
+        }
+
+        let mut ss_res = 0.0;
+        let mut ss_tot = 0.0;
+        let mut abs_sum = 0.0;
+        let mut pct_sum = 0.0;
+        let mut pct_count = 0;
+        let mut max_error: f32 = 0.0;
+        for row in &residuals.rows {
+            for (j, &target) in row.target.iter().enumerate() {
+                let error = row.prediction[j] - target;
+                ss_res += error * error;
+                ss_tot += (target - means[j]) * (target - means[j]);
+                abs_sum += error.abs();
+                max_error = max_error.max(error.abs());
+                if target != 0.0 {
+                    pct_sum += (error / target).abs();
+                    pct_count += 1;
+                }
+            }
+        }
+
+        let n = (residuals.rows.len() * cols).max(1) as f32;
+        Regression {
+            r2: if ss_tot == 0.0 {
+                0.0
+            } else {
+                1.0 - ss_res / ss_tot
+            },
+            rmse: (ss_res / n).sqrt(),
+            mae: abs_sum / n,
+            mape: 100.0 * pct_sum / pct_count.max(1) as f32,
+            max_error,
+        }
+    }
+}
+
+impl fmt::Display for Regression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{:>9} {:>10.6}", "r2", self.r2)?;
+        writeln!(f, "{:>9} {:>10.6}", "rmse", self.rmse)?;
+        writeln!(f, "{:>9} {:>10.6}", "mae", self.mae)?;
+        writeln!(f, "{:>9} {:>9.3}%", "mape", self.mape)?;
+        writeln!(f, "{:>9} {:>10.6}", "max error", self.max_error)
+    }
+}
+
+/// What the network predicted for one row next to what it should have.
+#[derive(Clone, Debug)]
+pub struct Residual {
+    pub input: Vec<f32>,
+    pub target: Vec<f32>,
+    pub prediction: Vec<f32>,
+}
+
+impl Residual {
+    /// `prediction - target` for every output.
+    pub fn error(&self) -> Vec<f32> {
+        self.prediction
+            .iter()
+            .zip(&self.target)
+            .map(|(p, t)| p - t)
+            .collect()
+    }
+}
+
+/// Per-row residuals of a network over a data set.
+#[derive(Clone, Debug)]
+pub struct Residuals {
+    pub rows: Vec<Residual>,
+}
+
+impl Residuals {
+    pub fn new(nn: &NN, input: &Mat, targets: &Mat) -> Residuals {
+        assert_eq!(input.rows, targets.rows);
+        let predictions = NN::predict(nn, input);
+        assert_eq!(predictions.cols, targets.cols);
+
+        let rows = (0..input.rows)
+            .map(|i| Residual {
+                input: input.data[i].clone(),
+                target: targets.data[i].clone(),
+                prediction: predictions.data[i].clone(),
+            })
+            .collect();
+
+        Residuals { rows }
+    }
+
+    /// Writes one line per row with `input_*`, `target_*`, `prediction_*` and
+    /// `error_*` columns after a header line.
+    pub fn write_csv<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        let Some(first) = self.rows.first() else {
+            return Ok(());
+        };
+
+        let mut header = Vec::new();
+        for (name, len) in [
+            ("input", first.input.len()),
+            ("target", first.target.len()),
+            ("prediction", first.prediction.len()),
+            ("error", first.target.len()),
+        ] {
+            header.extend((0..len).map(|i| format!("{}_{}", name, i)));
+        }
+        writeln!(w, "{}", header.join(","))?;
+
+        for row in &self.rows {
+            let error = row.error();
+            let fields: Vec<String> = row
+                .input
+                .iter()
+                .chain(&row.target)
+                .chain(&row.prediction)
+                .chain(&error)
+                .map(|val| val.to_string())
+                .collect();
+            writeln!(w, "{}", fields.join(","))?;
+        }
+
+        Ok(())
+    }
+
+    pub fn save_csv<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut w = BufWriter::new(File::create(path)?);
+        self.write_csv(&mut w)?;
+        w.flush()
+    }
+}
+
+// Probability that a random row of `class` scores higher than a random row of
+// another class, counting ties as half.
+fn roc_auc(labels: &Mat, predictions: &Mat, class: usize) -> Option<f32> {
+    let binary = labels.cols == 1;
+    let mut scored: Vec<(f32, bool)> = labels
+        .data
+        .iter()
+        .zip(&predictions.data)
+        .map(|(label, prediction)| {
+            if binary {
+                (prediction[0], class_of(label) == 1)
+            } else {
+                (prediction[class], class_of(label) == class)
+            }
+        })
+        .collect();
+
+    let positives = scored.iter().filter(|(_, positive)| *positive).count();
+    let negatives = scored.len() - positives;
+    if positives == 0 || negatives == 0 {
+        return None;
+    }
+
+    scored.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    // Sum of the positive rows' ranks, averaging the ranks of tied scores
+    let mut rank_sum = 0.0;
+    let mut i = 0;
+    while i < scored.len() {
+        let mut j = i;
+        while j < scored.len() && scored[j].0 == scored[i].0 {
+            j += 1;
+        }
+        let rank = (i + j + 1) as f32 / 2.0;
+        rank_sum += rank * scored[i..j].iter().filter(|(_, p)| *p).count() as f32;
+        i = j;
+    }
+
+    let positives = positives as f32;
+    let negatives = negatives as f32;
+    Some((rank_sum - positives * (positives + 1.0) / 2.0) / (positives * negatives))
+}
+
+fn ratio(a: usize, b: usize) -> f32 {
+    if b == 0 {
+        0.0
+    } else {
+        a as f32 / b as f32
+    }
+}
+
+fn harmonic_mean(a: f32, b: f32) -> f32 {
+    if a + b == 0.0 {
+        0.0
+    } else {
+        2.0 * a * b / (a + b)
+    }
+}
@@ -0,0 +1,186 @@
+use std::{fmt, fs, io, path::Path};
+
+use serde_json::{json, Value};
+
+use crate::{csv::Schema, preprocess::Pipeline, Mat, NN};
+
+/// A network together with the preprocessing its data went through, so it
+/// can be saved, loaded and fed raw rows.
+#[derive(Clone, Debug)]
+pub struct Model {
+    pub nn: NN,
+    /// Applied to inputs before the network sees them.
+    pub input: Pipeline,
+    /// Applied to training targets, undone on the network's outputs.
+    pub output: Pipeline,
+    /// How the columns of the CSV file it was trained on became numbers,
+    /// `None` when it wasn't trained on one.
+    pub columns: Option<Schema>,
+}
+
+#[derive(Debug)]
+pub enum ModelError {
+    Io(io::Error),
+    Json(serde_json::Error),
+    /// Valid JSON that doesn't describe a model.
+    Format(String),
+}
+
+impl fmt::Display for ModelError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ModelError::Io(err) => write!(f, "{}", err),
+            ModelError::Json(err) => write!(f, "{}", err),
+            ModelError::Format(msg) => write!(f, "not a model file: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for ModelError {}
+
+impl From<io::Error> for ModelError {
+    fn from(err: io::Error) -> ModelError {
+        ModelError::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for ModelError {
+    fn from(err: serde_json::Error) -> ModelError {
+        ModelError::Json(err)
+    }
+}
+
+impl Model {
+    pub fn new(nn: NN) -> Model {
+        Model {
+            nn,
+            input: Pipeline::default(),
+            output: Pipeline::default(),
+            columns: None,
+        }
+    }
+
+    /// Runs raw input rows through the input pipeline and the network, and
+    /// maps the outputs back with the output pipeline.
+    pub fn predict(&self, input: &Mat) -> Mat {
+        let output = NN::predict(&self.nn, &self.input.transform(input));
+        self.output.inverse(&output)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), ModelError> {
+        fs::write(path, serde_json::to_string_pretty(&self.to_json())?)?;
+        Ok(())
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Model, ModelError> {
+        Self::from_json(&serde_json::from_str(&fs::read_to_string(path)?)?)
+    }
+
+    pub fn to_json(&self) -> Value {
+        let arch: Vec<usize> = self.nn.activations.iter().map(|a| a.cols).collect();
+        json!({
+            "arch": arch,
+            "weights": self.nn.weights.iter().map(|w| &w.data).collect::<Vec<_>>(),
+            "biases": self.nn.biases.iter().map(|b| &b.data[0]).collect::<Vec<_>>(),
+            "input": self.input.to_json(),
+            "output": self.output.to_json(),
+            "columns": self.columns.as_ref().map(Schema::to_json),
+        })
+    }
+
+    pub fn from_json(value: &Value) -> Result<Model, ModelError> {
+        let arch: Vec<usize> = value["arch"]
+            .as_array()
+            .filter(|arch| !arch.is_empty())
+            .ok_or_else(|| ModelError::Format("`arch` must be a non-empty array".to_owned()))?
+            .iter()
+            .map(|v| v.as_u64().map(|v| v as usize))
+            .collect::<Option<_>>()
+            .ok_or_else(|| ModelError::Format("`arch` must hold layer sizes".to_owned()))?;
+
+        let mut nn = NN::new(&arch);
+        for i in 0..nn.count - 1 {
+            let weights = &value["weights"][i];
+            for (j, row) in nn.weights[i].data.iter_mut().enumerate() {
+                read_row(&weights[j], row, &format!("weights[{}][{}]", i, j))?;
+            }
+            read_row(
+                &value["biases"][i],
+                &mut nn.biases[i].data[0],
+                &format!("biases[{}]", i),
+            )?;
+        }
+
+        let pipeline = |key: &str| match &value[key] {
+            Value::Null => Ok(Pipeline::default()),
+            steps => Pipeline::from_json(steps)
+                .map_err(|err| ModelError::Format(format!("`{}`: {}", key, err))),
+        };
+
+        let columns = match &value["columns"] {
+            Value::Null => None,
+            columns => Some(
+                Schema::from_json(columns)
+                    .map_err(|err| ModelError::Format(format!("`columns`: {}", err)))?,
+            ),
+        };
+
+        let model = Model {
+            nn,
+            input: pipeline("input")?,
+            output: pipeline("output")?,
+            columns,
+        };
+        model.check_widths()?;
+        Ok(model)
+    }
+
+    // The pipelines and columns have to line up with the network's layers,
+    // or predicting would panic
+    fn check_widths(&self) -> Result<(), ModelError> {
+        let nn = &self.nn;
+        let inputs = self
+            .input
+            .input_cols(nn.activations[0].cols)
+            .ok_or_else(|| {
+                ModelError::Format(format!(
+                    "`input` doesn't fit the network's {} inputs",
+                    nn.activations[0].cols
+                ))
+            })?;
+        let outputs = nn.activations[nn.count - 1].cols;
+        let targets = self.output.input_cols(outputs).ok_or_else(|| {
+            ModelError::Format(format!(
+                "`output` doesn't fit the network's {} outputs",
+                outputs
+            ))
+        })?;
+
+        if let Some(columns) = &self.columns {
+            if columns.input_width() != inputs || columns.target_width() != targets {
+                return Err(ModelError::Format(format!(
+                    "`columns` encode {} inputs and {} targets, the pipelines take {} and {}",
+                    columns.input_width(),
+                    columns.target_width(),
+                    inputs,
+                    targets
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+fn read_row(value: &Value, row: &mut [f32], name: &str) -> Result<(), ModelError> {
+    let values = value
+        .as_array()
+        .filter(|values| values.len() == row.len())
+        .ok_or_else(|| ModelError::Format(format!("`{}` must hold {} numbers", name, row.len())))?;
+    for (dst, src) in row.iter_mut().zip(values) {
+        *dst = src
+            .as_f64()
+            .ok_or_else(|| ModelError::Format(format!("`{}` must hold numbers", name)))?
+            as f32;
+    }
+    Ok(())
+}
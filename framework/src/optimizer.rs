@@ -0,0 +1,67 @@
+use crate::NN;
+
+/// Applies a gradient from [`NN::backprop`] to the network's weights.
+pub trait Optimizer {
+    fn update(&mut self, nn: &mut NN, gradient: &NN, rate: f32);
+}
+
+impl<O: Optimizer + ?Sized> Optimizer for Box<O> {
+    fn update(&mut self, nn: &mut NN, gradient: &NN, rate: f32) {
+        (**self).update(nn, gradient, rate)
+    }
+}
+
+/// Plain gradient descent, a step of `rate` against the gradient.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Sgd;
+
+impl Optimizer for Sgd {
+    fn update(&mut self, nn: &mut NN, gradient: &NN, rate: f32) {
+        NN::learn(nn, gradient, rate);
+    }
+}
+
+/// Gradient descent that keeps `momentum` of the previous step going.
+#[derive(Clone, Debug)]
+pub struct Momentum {
+    pub momentum: f32,
+    // the last step taken, allocated on the first update
+    velocity: Option<NN>,
+}
+
+impl Momentum {
+    pub fn new(momentum: f32) -> Momentum {
+        Momentum {
+            momentum,
+            velocity: None,
+        }
+    }
+}
+
+impl Optimizer for Momentum {
+    fn update(&mut self, nn: &mut NN, gradient: &NN, rate: f32) {
+        let velocity = self.velocity.get_or_insert_with(|| {
+            let mut velocity = gradient.clone();
+            NN::zero(&mut velocity);
+            velocity
+        });
+
+        for i in 0..nn.count - 1 {
+            for j in 0..nn.weights[i].rows {
+                for k in 0..nn.weights[i].cols {
+                    let v = &mut velocity.weights[i].data[j][k];
+                    *v = self.momentum * *v + rate * gradient.weights[i].data[j][k];
+                    nn.weights[i].data[j][k] -= *v;
+                }
+            }
+
+            for j in 0..nn.biases[i].rows {
+                for k in 0..nn.biases[i].cols {
+                    let v = &mut velocity.biases[i].data[j][k];
+                    *v = self.momentum * *v + rate * gradient.biases[i].data[j][k];
+                    nn.biases[i].data[j][k] -= *v;
+                }
+            }
+        }
+    }
+}
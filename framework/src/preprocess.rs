@@ -0,0 +1,461 @@
+use serde_json::{json, Value};
+
+use crate::Mat;
+
+/// A fit/transform preprocessing step. Create one with its constructor, then
+/// [`Transform::fit`] it on the training data before transforming anything.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Transform {
+    /// Scales every column into `0..=1`.
+    MinMax { min: Vec<f32>, max: Vec<f32> },
+    /// Shifts and scales every column to zero mean and unit variance.
+    Standard { mean: Vec<f32>, std: Vec<f32> },
+    /// Like `Standard` but with the median and interquartile range, so
+    /// outliers don't skew the scaling.
+    Robust { median: Vec<f32>, iqr: Vec<f32> },
+    /// Replaces `column` with one column per distinct value seen in it.
+    OneHot { column: usize, categories: Vec<f32> },
+    /// Replaces the values of `column` with their index among the distinct
+    /// values seen in it. Values that weren't seen get the index of the
+    /// closest one that was, the lower one on a tie.
+    Label { column: usize, classes: Vec<f32> },
+    /// Appends every product of up to `degree` columns to the original
+    /// `cols` columns.
+    Polynomial { degree: usize, cols: usize },
+}
+
+impl Transform {
+    pub fn min_max() -> Transform {
+        Transform::MinMax {
+            min: Vec::new(),
+            max: Vec::new(),
+        }
+    }
+
+    pub fn standard() -> Transform {
+        Transform::Standard {
+            mean: Vec::new(),
+            std: Vec::new(),
+        }
+    }
+
+    pub fn robust() -> Transform {
+        Transform::Robust {
+            median: Vec::new(),
+            iqr: Vec::new(),
+        }
+    }
+
+    pub fn one_hot(column: usize) -> Transform {
+        Transform::OneHot {
+            column,
+            categories: Vec::new(),
+        }
+    }
+
+    pub fn label(column: usize) -> Transform {
+        Transform::Label {
+            column,
+            classes: Vec::new(),
+        }
+    }
+
+    pub fn polynomial(degree: usize) -> Transform {
+        assert!(degree >= 1);
+        Transform::Polynomial { degree, cols: 0 }
+    }
+
+    pub fn fit(&mut self, data: &Mat) {
+        match self {
+            Transform::MinMax { min, max } => {
+                *min = columns(data)
+                    .map(|col| col.iter().cloned().fold(f32::INFINITY, f32::min))
+                    .collect();
+                *max = columns(data)
+                    .map(|col| col.iter().cloned().fold(f32::NEG_INFINITY, f32::max))
+                    .collect();
+            }
+            Transform::Standard { mean, std } => {
+                *mean = columns(data).map(|col| average(&col)).collect();
+                *std = columns(data)
+                    .zip(mean.iter())
+                    .map(|(col, m)| {
+                        average(&col.iter().map(|v| (v - m) * (v - m)).collect::<Vec<_>>()).sqrt()
+                    })
+                    .collect();
+            }
+            Transform::Robust { median, iqr } => {
+                let sorted: Vec<Vec<f32>> = columns(data)
+                    .map(|mut col| {
+                        col.sort_by(f32::total_cmp);
+                        col
+                    })
+                    .collect();
+                *median = sorted.iter().map(|col| quantile(col, 0.5)).collect();
+                *iqr = sorted
+                    .iter()
+                    .map(|col| quantile(col, 0.75) - quantile(col, 0.25))
+                    .collect();
+            }
+            Transform::OneHot { column, categories } => *categories = distinct(data, *column),
+            Transform::Label { column, classes } => *classes = distinct(data, *column),
+            Transform::Polynomial { cols, .. } => *cols = data.cols,
+        }
+    }
+
+    pub fn transform(&self, data: &Mat) -> Mat {
+        match self {
+            Transform::MinMax { min, max } => {
+                map_columns(data, |j, v| (v - min[j]) / nonzero(max[j] - min[j]))
+            }
+            Transform::Standard { mean, std } => {
+                map_columns(data, |j, v| (v - mean[j]) / nonzero(std[j]))
+            }
+            Transform::Robust { median, iqr } => {
+                map_columns(data, |j, v| (v - median[j]) / nonzero(iqr[j]))
+            }
+            Transform::OneHot { column, categories } => map_rows(
+                data,
+                (data.cols + categories.len()).saturating_sub(1),
+                |row| {
+                    let mut out = row[..*column].to_vec();
+                    out.extend(
+                        categories
+                            .iter()
+                            .map(|c| (*c == row[*column]) as i32 as f32),
+                    );
+                    out.extend_from_slice(&row[*column + 1..]);
+                    out
+                },
+            ),
+            Transform::Label { column, classes } => map_rows(data, data.cols, |row| {
+                let mut out = row.to_vec();
+                out[*column] = closest(classes, row[*column]) as f32;
+                out
+            }),
+            Transform::Polynomial { degree, cols } => {
+                assert_eq!(data.cols, *cols);
+                let terms = monomials(*cols, *degree);
+                map_rows(data, terms.len(), |row| {
+                    terms
+                        .iter()
+                        .map(|term| term.iter().map(|&j| row[j]).product())
+                        .collect()
+                })
+            }
+        }
+    }
+
+    /// Undoes [`Transform::transform`], for turning network outputs back into
+    /// the units of the original data.
+    pub fn inverse(&self, data: &Mat) -> Mat {
+        match self {
+            Transform::MinMax { min, max } => {
+                map_columns(data, |j, v| v * nonzero(max[j] - min[j]) + min[j])
+            }
+            Transform::Standard { mean, std } => {
+                map_columns(data, |j, v| v * nonzero(std[j]) + mean[j])
+            }
+            Transform::Robust { median, iqr } => {
+                map_columns(data, |j, v| v * nonzero(iqr[j]) + median[j])
+            }
+            Transform::OneHot { column, categories } => map_rows(
+                data,
+                (data.cols + 1).saturating_sub(categories.len()),
+                |row| {
+                    let encoded = &row[*column..*column + categories.len()];
+                    let mut best = 0;
+                    for (i, val) in encoded.iter().enumerate() {
+                        if *val > encoded[best] {
+                            best = i;
+                        }
+                    }
+
+                    let mut out = row[..*column].to_vec();
+                    out.push(categories[best]);
+                    out.extend_from_slice(&row[*column + categories.len()..]);
+                    out
+                },
+            ),
+            // without any classes fitted there's nothing to map back to
+            Transform::Label { classes, .. } if classes.is_empty() => data.clone(),
+            Transform::Label { column, classes } => map_rows(data, data.cols, |row| {
+                let mut out = row.to_vec();
+                let index = row[*column].round().clamp(0.0, (classes.len() - 1) as f32);
+                out[*column] = classes[index as usize];
+                out
+            }),
+            // the original columns come first
+            Transform::Polynomial { cols, .. } => {
+                map_rows(data, *cols, |row| row[..*cols].to_vec())
+            }
+        }
+    }
+
+    /// Columns of the data this transform turns into `cols` columns, `None`
+    /// when its fitted state can't produce that many.
+    pub fn input_cols(&self, cols: usize) -> Option<usize> {
+        match self {
+            Transform::MinMax { min: a, max: b }
+            | Transform::Standard { mean: a, std: b }
+            | Transform::Robust { median: a, iqr: b } => {
+                (a.len() == cols && b.len() == cols).then_some(cols)
+            }
+            Transform::OneHot { column, categories } => (!categories.is_empty()
+                && column + categories.len() <= cols)
+                .then(|| cols - categories.len() + 1),
+            Transform::Label { column, classes } => {
+                (!classes.is_empty() && *column < cols).then_some(cols)
+            }
+            Transform::Polynomial {
+                degree,
+                cols: original,
+            } => (*degree >= 1 && monomial_count(*original, *degree, cols) == Some(cols))
+                .then_some(*original),
+        }
+    }
+
+    pub fn to_json(&self) -> Value {
+        match self {
+            Transform::MinMax { min, max } => json!({"type": "min_max", "min": min, "max": max}),
+            Transform::Standard { mean, std } => {
+                json!({"type": "standard", "mean": mean, "std": std})
+            }
+            Transform::Robust { median, iqr } => {
+                json!({"type": "robust", "median": median, "iqr": iqr})
+            }
+            Transform::OneHot { column, categories } => {
+                json!({"type": "one_hot", "column": column, "categories": categories})
+            }
+            Transform::Label { column, classes } => {
+                json!({"type": "label", "column": column, "classes": classes})
+            }
+            Transform::Polynomial { degree, cols } => {
+                json!({"type": "polynomial", "degree": degree, "cols": cols})
+            }
+        }
+    }
+
+    pub fn from_json(value: &Value) -> Result<Transform, String> {
+        let floats = |key: &str| -> Result<Vec<f32>, String> {
+            value[key]
+                .as_array()
+                .ok_or_else(|| format!("`{}` must be an array", key))?
+                .iter()
+                .map(|v| {
+                    v.as_f64()
+                        .map(|v| v as f32)
+                        .ok_or_else(|| format!("`{}` must hold numbers", key))
+                })
+                .collect()
+        };
+        let int = |key: &str| -> Result<usize, String> {
+            value[key]
+                .as_u64()
+                .map(|v| v as usize)
+                .ok_or_else(|| format!("`{}` must be a non-negative integer", key))
+        };
+
+        match value["type"].as_str() {
+            Some("min_max") => Ok(Transform::MinMax {
+                min: floats("min")?,
+                max: floats("max")?,
+            }),
+            Some("standard") => Ok(Transform::Standard {
+                mean: floats("mean")?,
+                std: floats("std")?,
+            }),
+            Some("robust") => Ok(Transform::Robust {
+                median: floats("median")?,
+                iqr: floats("iqr")?,
+            }),
+            Some("one_hot") => Ok(Transform::OneHot {
+                column: int("column")?,
+                categories: floats("categories")?,
+            }),
+            Some("label") => Ok(Transform::Label {
+                column: int("column")?,
+                classes: floats("classes")?,
+            }),
+            Some("polynomial") => Ok(Transform::Polynomial {
+                degree: int("degree")?,
+                cols: int("cols")?,
+            }),
+            Some(other) => Err(format!("unknown transform `{}`", other)),
+            None => Err("transform without a `type`".to_owned()),
+        }
+    }
+}
+
+/// Transforms applied one after the other, each fitted on the output of the
+/// ones before it.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Pipeline {
+    pub steps: Vec<Transform>,
+}
+
+impl Pipeline {
+    pub fn new(steps: Vec<Transform>) -> Pipeline {
+        Pipeline { steps }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.steps.is_empty()
+    }
+
+    pub fn fit(&mut self, data: &Mat) {
+        self.fit_transform(data);
+    }
+
+    /// Fits every step and returns the fully transformed data.
+    pub fn fit_transform(&mut self, data: &Mat) -> Mat {
+        let mut data = data.clone();
+        for step in &mut self.steps {
+            step.fit(&data);
+            data = step.transform(&data);
+        }
+        data
+    }
+
+    pub fn transform(&self, data: &Mat) -> Mat {
+        let mut data = data.clone();
+        for step in &self.steps {
+            data = step.transform(&data);
+        }
+        data
+    }
+
+    pub fn inverse(&self, data: &Mat) -> Mat {
+        let mut data = data.clone();
+        for step in self.steps.iter().rev() {
+            data = step.inverse(&data);
+        }
+        data
+    }
+
+    /// Columns of the data the pipeline turns into `cols` columns, `None`
+    /// when its steps don't fit together or can't produce that many.
+    pub fn input_cols(&self, cols: usize) -> Option<usize> {
+        self.steps
+            .iter()
+            .rev()
+            .try_fold(cols, |cols, step| step.input_cols(cols))
+    }
+
+    pub fn to_json(&self) -> Value {
+        Value::Array(self.steps.iter().map(Transform::to_json).collect())
+    }
+
+    pub fn from_json(value: &Value) -> Result<Pipeline, String> {
+        let steps = value
+            .as_array()
+            .ok_or("a pipeline must be an array of transforms")?
+            .iter()
+            .map(Transform::from_json)
+            .collect::<Result<_, _>>()?;
+        Ok(Pipeline { steps })
+    }
+}
+
+fn columns(data: &Mat) -> impl Iterator<Item = Vec<f32>> + '_ {
+    (0..data.cols).map(move |j| data.data.iter().map(|row| row[j]).collect())
+}
+
+fn map_columns(data: &Mat, f: impl Fn(usize, f32) -> f32) -> Mat {
+    map_rows(data, data.cols, |row| {
+        row.iter().enumerate().map(|(j, v)| f(j, *v)).collect()
+    })
+}
+
+// `cols` is the width of the rows `f` returns, which keeps it even when
+// there are no rows
+fn map_rows(data: &Mat, cols: usize, f: impl Fn(&[f32]) -> Vec<f32>) -> Mat {
+    Mat {
+        rows: data.rows,
+        cols,
+        data: data.data.iter().map(|row| f(row)).collect(),
+    }
+}
+
+fn average(values: &[f32]) -> f32 {
+    if values.is_empty() {
+        0.0
+    } else {
+        values.iter().sum::<f32>() / values.len() as f32
+    }
+}
+
+// Linear interpolation between the closest ranks of an already sorted column
+fn quantile(sorted: &[f32], q: f32) -> f32 {
+    if sorted.is_empty() {
+        return 0.0;
+    }
+    let pos = q * (sorted.len() - 1) as f32;
+    let (lo, hi) = (pos.floor() as usize, pos.ceil() as usize);
+    sorted[lo] + (sorted[hi] - sorted[lo]) * (pos - lo as f32)
+}
+
+fn distinct(data: &Mat, column: usize) -> Vec<f32> {
+    let mut values: Vec<f32> = data.data.iter().map(|row| row[column]).collect();
+    values.sort_by(f32::total_cmp);
+    values.dedup();
+    values
+}
+
+// Index of the class closest to `value`, the first one on a tie
+fn closest(classes: &[f32], value: f32) -> usize {
+    let mut best = 0;
+    for (i, class) in classes.iter().enumerate() {
+        if (class - value).abs() < (classes[best] - value).abs() {
+            best = i;
+        }
+    }
+    best
+}
+
+// A constant column would divide by zero, leave it unscaled instead
+fn nonzero(val: f32) -> f32 {
+    if val == 0.0 {
+        1.0
+    } else {
+        val
+    }
+}
+
+// How many terms `monomials` gives, `None` once it's past `limit` so huge
+// degrees from a file don't take forever
+fn monomial_count(cols: usize, degree: usize, limit: usize) -> Option<usize> {
+    if cols == 0 {
+        return Some(0);
+    }
+    // binomial(cols + degree, degree) - 1, the products of 0 to `degree`
+    // columns without the empty one
+    let mut count: usize = 1;
+    for k in 1..=degree {
+        count = count.checked_mul(cols + k)? / k;
+        if count - 1 > limit {
+            return None;
+        }
+    }
+    Some(count - 1)
+}
+
+// Column indices of every product of 1 to `degree` columns, lowest degree
+// first and without repeating the same product in another order
+fn monomials(cols: usize, degree: usize) -> Vec<Vec<usize>> {
+    let mut terms: Vec<Vec<usize>> = (0..cols).map(|j| vec![j]).collect();
+    let mut last = terms.clone();
+    for _ in 1..degree {
+        let mut next = Vec::new();
+        for term in &last {
+            for j in *term.last().unwrap()..cols {
+                let mut longer = term.clone();
+                longer.push(j);
+                next.push(longer);
+            }
+        }
+        terms.extend(next.iter().cloned());
+        last = next;
+    }
+    terms
+}
@@ -0,0 +1,8 @@
+use rand::Rng;
+
+// Standard normal sample, Box-Muller
+pub(crate) fn gaussian<R: Rng>(rng: &mut R) -> f32 {
+    let u: f32 = rng.gen_range(f32::EPSILON..1.0);
+    let v: f32 = rng.gen();
+    (-2.0 * u.ln()).sqrt() * (std::f32::consts::TAU * v).cos()
+}
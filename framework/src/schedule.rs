@@ -0,0 +1,244 @@
+use std::f32::consts::PI;
+
+/// Decides the learning rate used for each epoch.
+///
+/// `cost` is the training cost measured after the previous epoch, which lets
+/// schedules such as [`ReduceOnPlateau`] react to how training is going.
+pub trait Schedule {
+    fn rate(&mut self, epoch: usize, cost: f32) -> f32;
+}
+
+impl<S: Schedule + ?Sized> Schedule for Box<S> {
+    fn rate(&mut self, epoch: usize, cost: f32) -> f32 {
+        (**self).rate(epoch, cost)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Constant {
+    pub rate: f32,
+}
+
+impl Constant {
+    pub fn new(rate: f32) -> Constant {
+        Constant { rate }
+    }
+}
+
+impl Schedule for Constant {
+    fn rate(&mut self, _epoch: usize, _cost: f32) -> f32 {
+        self.rate
+    }
+}
+
+/// Multiplies the rate by `factor` every `step_size` epochs.
+#[derive(Clone, Debug)]
+pub struct StepDecay {
+    pub initial: f32,
+    pub factor: f32,
+    pub step_size: usize,
+}
+
+impl StepDecay {
+    pub fn new(initial: f32, factor: f32, step_size: usize) -> StepDecay {
+        assert!(step_size > 0);
+        StepDecay {
+            initial,
+            factor,
+            step_size,
+        }
+    }
+}
+
+impl Schedule for StepDecay {
+    fn rate(&mut self, epoch: usize, _cost: f32) -> f32 {
+        self.initial * self.factor.powi((epoch / self.step_size) as i32)
+    }
+}
+
+/// Multiplies the rate by `gamma` every epoch.
+#[derive(Clone, Debug)]
+pub struct Exponential {
+    pub initial: f32,
+    pub gamma: f32,
+}
+
+impl Exponential {
+    pub fn new(initial: f32, gamma: f32) -> Exponential {
+        Exponential { initial, gamma }
+    }
+}
+
+impl Schedule for Exponential {
+    fn rate(&mut self, epoch: usize, _cost: f32) -> f32 {
+        self.initial * self.gamma.powi(epoch as i32)
+    }
+}
+
+/// Cosine annealing from `max` down to `min` over `period` epochs, then
+/// restarting at `max`. Each restart multiplies the period by `period_mult`.
+#[derive(Clone, Debug)]
+pub struct CosineRestarts {
+    pub max: f32,
+    pub min: f32,
+    pub period: usize,
+    pub period_mult: usize,
+}
+
+impl CosineRestarts {
+    pub fn new(max: f32, min: f32, period: usize) -> CosineRestarts {
+        assert!(period > 0);
+        CosineRestarts {
+            max,
+            min,
+            period,
+            period_mult: 1,
+        }
+    }
+}
+
+impl Schedule for CosineRestarts {
+    fn rate(&mut self, epoch: usize, _cost: f32) -> f32 {
+        // the fields can be changed after `new`, a zero period would never
+        // finish a cycle
+        let period = self.period.max(1) as f64;
+        let mult = self.period_mult.max(1) as f64;
+        let epoch = epoch as f64;
+
+        let (t, length) = if mult == 1.0 {
+            (epoch % period, period)
+        } else {
+            // cycle k starts at period * (mult^k - 1) / (mult - 1)
+            let start = |k: i32| period * (mult.powi(k) - 1.0) / (mult - 1.0);
+            let mut k = ((epoch * (mult - 1.0) / period + 1.0).ln() / mult.ln()).floor() as i32;
+            // rounding can land a cycle off right at a restart
+            if start(k) > epoch {
+                k -= 1;
+            } else if start(k + 1) <= epoch {
+                k += 1;
+            }
+            (epoch - start(k), start(k + 1) - start(k))
+        };
+
+        let progress = (t / length) as f32;
+        self.min + (self.max - self.min) * (1.0 + (PI * progress).cos()) / 2.0
+    }
+}
+
+/// Ramps the rate linearly from `start` up to whatever `inner` returns over
+/// the first `epochs` epochs, then hands over to `inner`.
+#[derive(Clone, Debug)]
+pub struct Warmup<S> {
+    pub inner: S,
+    pub epochs: usize,
+    pub start: f32,
+}
+
+impl<S: Schedule> Warmup<S> {
+    pub fn new(inner: S, epochs: usize) -> Warmup<S> {
+        Warmup {
+            inner,
+            epochs,
+            start: 0.0,
+        }
+    }
+}
+
+impl<S: Schedule> Schedule for Warmup<S> {
+    fn rate(&mut self, epoch: usize, cost: f32) -> f32 {
+        let target = self.inner.rate(epoch, cost);
+        if epoch >= self.epochs {
+            return target;
+        }
+
+        let t = (epoch + 1) as f32 / self.epochs as f32;
+        self.start + (target - self.start) * t
+    }
+}
+
+/// The one-cycle policy: warm up from `max / div_factor` to `max` over the
+/// first `pct_start` of `total` epochs, then anneal down to
+/// `max / final_div_factor`.
+#[derive(Clone, Debug)]
+pub struct OneCycle {
+    pub max: f32,
+    pub total: usize,
+    pub pct_start: f32,
+    pub div_factor: f32,
+    pub final_div_factor: f32,
+}
+
+impl OneCycle {
+    pub fn new(max: f32, total: usize) -> OneCycle {
+        assert!(total > 0);
+        OneCycle {
+            max,
+            total,
+            pct_start: 0.3,
+            div_factor: 25.0,
+            final_div_factor: 1e4,
+        }
+    }
+}
+
+impl Schedule for OneCycle {
+    fn rate(&mut self, epoch: usize, _cost: f32) -> f32 {
+        let initial = self.max / self.div_factor;
+        let last = self.max / self.final_div_factor;
+        let up = ((self.total as f32 * self.pct_start) as usize).max(1);
+
+        let anneal = |from: f32, to: f32, t: f32| to + (from - to) * (1.0 + (PI * t).cos()) / 2.0;
+
+        if epoch < up {
+            anneal(initial, self.max, epoch as f32 / up as f32)
+        } else {
+            let down = self.total.saturating_sub(up).max(1);
+            let t = ((epoch - up) as f32 / down as f32).min(1.0);
+            anneal(self.max, last, t)
+        }
+    }
+}
+
+/// Multiplies the rate by `factor` whenever the cost hasn't improved by more
+/// than `min_delta` for `patience` epochs, never going below `min_rate`.
+#[derive(Clone, Debug)]
+pub struct ReduceOnPlateau {
+    pub rate: f32,
+    pub factor: f32,
+    pub patience: usize,
+    pub min_delta: f32,
+    pub min_rate: f32,
+    best: f32,
+    wait: usize,
+}
+
+impl ReduceOnPlateau {
+    pub fn new(rate: f32, factor: f32, patience: usize) -> ReduceOnPlateau {
+        ReduceOnPlateau {
+            rate,
+            factor,
+            patience,
+            min_delta: 0.0,
+            min_rate: 0.0,
+            best: f32::INFINITY,
+            wait: 0,
+        }
+    }
+}
+
+impl Schedule for ReduceOnPlateau {
+    fn rate(&mut self, _epoch: usize, cost: f32) -> f32 {
+        if cost < self.best - self.min_delta {
+            self.best = cost;
+            self.wait = 0;
+        } else {
+            self.wait += 1;
+            if self.wait >= self.patience {
+                self.rate = (self.rate * self.factor).max(self.min_rate);
+                self.wait = 0;
+            }
+        }
+
+        self.rate
+    }
+}
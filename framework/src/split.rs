@@ -0,0 +1,113 @@
+use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
+
+use crate::Mat;
+
+/// Training rows and the rows held out from them.
+///
+/// Splitting twice gives a train/validation/test split: hold out the test set
+/// first, then split the remaining training rows again for validation.
+#[derive(Clone, Debug)]
+pub struct Split {
+    pub train_input: Mat,
+    pub train_output: Mat,
+    pub holdout_input: Mat,
+    pub holdout_output: Mat,
+}
+
+impl Split {
+    fn from_indices(input: &Mat, output: &Mat, train: &[usize], holdout: &[usize]) -> Split {
+        Split {
+            train_input: Mat::select_rows(input, train),
+            train_output: Mat::select_rows(output, train),
+            holdout_input: Mat::select_rows(input, holdout),
+            holdout_output: Mat::select_rows(output, holdout),
+        }
+    }
+}
+
+/// Shuffles the rows and holds out `ratio` of them.
+pub fn holdout(input: &Mat, output: &Mat, ratio: f32, seed: u64) -> Split {
+    assert_eq!(input.rows, output.rows);
+    assert!((0.0..=1.0).contains(&ratio));
+
+    let mut indices: Vec<usize> = (0..input.rows).collect();
+    indices.shuffle(&mut StdRng::seed_from_u64(seed));
+
+    let n = (input.rows as f32 * ratio).round() as usize;
+    let (held, train) = indices.split_at(n);
+    Split::from_indices(input, output, train, held)
+}
+
+/// Like [`holdout`], but holds out `ratio` of every class so both sides keep
+/// the same class balance. See [`class_of`] for how rows map to classes.
+pub fn stratified(input: &Mat, output: &Mat, ratio: f32, seed: u64) -> Split {
+    assert_eq!(input.rows, output.rows);
+    assert!((0.0..=1.0).contains(&ratio));
+
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut train = Vec::new();
+    let mut held = Vec::new();
+
+    for mut class in group_by_class(output) {
+        class.shuffle(&mut rng);
+        let n = (class.len() as f32 * ratio).round() as usize;
+        held.extend_from_slice(&class[..n]);
+        train.extend_from_slice(&class[n..]);
+    }
+
+    // Keep the classes interleaved instead of in blocks
+    train.shuffle(&mut rng);
+    held.shuffle(&mut rng);
+    Split::from_indices(input, output, &train, &held)
+}
+
+/// Splits the shuffled rows into `k` folds and returns one [`Split`] per fold,
+/// each holding out that fold and training on the rest.
+pub fn k_fold(input: &Mat, output: &Mat, k: usize, seed: u64) -> Vec<Split> {
+    assert_eq!(input.rows, output.rows);
+    assert!(k >= 2 && k <= input.rows);
+
+    let mut indices: Vec<usize> = (0..input.rows).collect();
+    indices.shuffle(&mut StdRng::seed_from_u64(seed));
+
+    (0..k)
+        .map(|fold| {
+            let start = fold * indices.len() / k;
+            let end = (fold + 1) * indices.len() / k;
+            let train: Vec<usize> = indices[..start]
+                .iter()
+                .chain(&indices[end..])
+                .cloned()
+                .collect();
+            Split::from_indices(input, output, &train, &indices[start..end])
+        })
+        .collect()
+}
+
+/// The class of a target row: the index of the largest output for one-hot
+/// targets, or the output rounded to 0 or 1 for a single column.
+pub fn class_of(row: &[f32]) -> usize {
+    if row.len() == 1 {
+        return (row[0] >= 0.5) as usize;
+    }
+
+    let mut best = 0;
+    for (i, val) in row.iter().enumerate() {
+        if *val > row[best] {
+            best = i;
+        }
+    }
+    best
+}
+
+fn group_by_class(output: &Mat) -> Vec<Vec<usize>> {
+    let mut classes: Vec<Vec<usize>> = Vec::new();
+    for (i, row) in output.data.iter().enumerate() {
+        let class = class_of(row);
+        if classes.len() <= class {
+            classes.resize(class + 1, Vec::new());
+        }
+        classes[class].push(i);
+    }
+    classes
+}
@@ -122,4 +122,1156 @@ mod tests {
         assert_eq!(nn.activations[1].data[0][0], 0.631_812_45);
         assert_eq!(nn.activations[1].data[0][1], 0.659_260_4);
     }
+
+    #[test]
+    fn test_schedule_step_decay() {
+        use crate::schedule::{Schedule, StepDecay};
+
+        let mut schedule = StepDecay::new(1.0, 0.5, 10);
+
+        assert_eq!(schedule.rate(0, 0.0), 1.0);
+        assert_eq!(schedule.rate(9, 0.0), 1.0);
+        assert_eq!(schedule.rate(10, 0.0), 0.5);
+        assert_eq!(schedule.rate(25, 0.0), 0.25);
+    }
+
+    #[test]
+    fn test_schedule_cosine_restarts() {
+        use crate::schedule::{CosineRestarts, Schedule};
+
+        let mut schedule = CosineRestarts::new(1.0, 0.0, 10);
+
+        assert_eq!(schedule.rate(0, 0.0), 1.0);
+        assert!((schedule.rate(5, 0.0) - 0.5).abs() < 1e-6);
+        assert_eq!(schedule.rate(10, 0.0), 1.0);
+        assert!((schedule.rate(1_000_000_005, 0.0) - 0.5).abs() < 1e-6);
+
+        // cycles of 10, 20, 40, ... epochs
+        schedule.period_mult = 2;
+        assert_eq!(schedule.rate(10, 0.0), 1.0);
+        assert!((schedule.rate(20, 0.0) - 0.5).abs() < 1e-6);
+        assert_eq!(schedule.rate(30, 0.0), 1.0);
+        assert!((schedule.rate(50, 0.0) - 0.5).abs() < 1e-6);
+        assert_eq!(schedule.rate(70, 0.0), 1.0);
+        assert_eq!(schedule.rate(10 * ((1 << 40) - 1), 0.0), 1.0);
+
+        // a period zeroed after `new` behaves like a period of one
+        schedule.period = 0;
+        schedule.period_mult = 1;
+        assert_eq!(schedule.rate(7, 0.0), 1.0);
+    }
+
+    #[test]
+    fn test_schedule_exponential() {
+        use crate::schedule::{Exponential, Schedule};
+
+        let mut schedule = Exponential::new(2.0, 0.5);
+
+        assert_eq!(schedule.rate(0, 0.0), 2.0);
+        assert_eq!(schedule.rate(1, 0.0), 1.0);
+        assert_eq!(schedule.rate(3, 0.0), 0.25);
+    }
+
+    #[test]
+    fn test_schedule_warmup() {
+        use crate::schedule::{Constant, Schedule, Warmup};
+
+        let mut schedule = Warmup::new(Constant::new(1.0), 4);
+
+        assert_eq!(schedule.rate(0, 0.0), 0.25);
+        assert_eq!(schedule.rate(2, 0.0), 0.75);
+        // the last warmup epoch reaches the inner rate, which takes over
+        // after it
+        assert_eq!(schedule.rate(3, 0.0), 1.0);
+        assert_eq!(schedule.rate(4, 0.0), 1.0);
+        assert_eq!(schedule.rate(100, 0.0), 1.0);
+    }
+
+    #[test]
+    fn test_schedule_one_cycle() {
+        use crate::schedule::{OneCycle, Schedule};
+
+        let mut schedule = OneCycle::new(1.0, 10);
+        let close = |a: f32, b: f32| (a - b).abs() < 1e-6;
+
+        assert!(close(schedule.rate(0, 0.0), 1.0 / 25.0));
+        assert!(schedule.rate(1, 0.0) < schedule.rate(2, 0.0));
+        // the peak is 30% of the way in
+        assert!(close(schedule.rate(3, 0.0), 1.0));
+        assert!(schedule.rate(4, 0.0) < 1.0);
+        assert!(close(schedule.rate(10, 0.0), 1e-4));
+        assert!(close(schedule.rate(20, 0.0), 1e-4));
+    }
+
+    #[test]
+    fn test_schedule_reduce_on_plateau() {
+        use crate::schedule::{ReduceOnPlateau, Schedule};
+
+        let mut schedule = ReduceOnPlateau::new(1.0, 0.5, 2);
+
+        assert_eq!(schedule.rate(0, 1.0), 1.0);
+        assert_eq!(schedule.rate(1, 0.5), 1.0);
+        assert_eq!(schedule.rate(2, 0.5), 1.0);
+        // the second epoch without improvement uses up the patience
+        assert_eq!(schedule.rate(3, 0.5), 0.5);
+        assert_eq!(schedule.rate(4, 0.5), 0.5);
+        assert_eq!(schedule.rate(5, 0.5), 0.25);
+        // improving resets the wait
+        assert_eq!(schedule.rate(6, 0.1), 0.25);
+        assert_eq!(schedule.rate(7, 0.1), 0.25);
+        assert_eq!(schedule.rate(8, 0.1), 0.125);
+    }
+
+    #[test]
+    fn test_trainer_callbacks() {
+        use crate::trainer::{Callback, Flow, Trainer};
+
+        #[derive(Default)]
+        struct Counter {
+            batches: usize,
+            epochs: usize,
+            improvements: usize,
+        }
+
+        impl Callback for Counter {
+            fn on_batch_end(&mut self, _trainer: &Trainer, _batch: usize) -> Flow {
+                self.batches += 1;
+                Flow::Continue
+            }
+
+            fn on_epoch_end(&mut self, trainer: &Trainer) -> Flow {
+                self.epochs += 1;
+                if trainer.epoch == 5 {
+                    Flow::Stop
+                } else {
+                    Flow::Continue
+                }
+            }
+
+            fn on_improvement(&mut self, _trainer: &Trainer) {
+                self.improvements += 1;
+            }
+        }
+
+        let t_input = Mat::new(&[&[0.0, 0.0], &[0.0, 1.0], &[1.0, 0.0], &[1.0, 1.0]]);
+        let t_output = Mat::new(&[&[0.0], &[1.0], &[1.0], &[0.0]]);
+        let mut nn = NN::new(&[2, 2, 1]);
+        NN::randomize(&mut nn, -1.0, 1.0);
+
+        let mut trainer = Trainer::new(nn, t_input, t_output);
+        trainer.batch_size = 2;
+        let mut counter = Counter::default();
+        trainer.fit(100, &mut [&mut counter]);
+
+        assert_eq!(trainer.epoch, 5);
+        assert_eq!(counter.epochs, 5);
+        assert_eq!(counter.batches, 10);
+        assert!(counter.improvements <= 5);
+        assert_eq!(trainer.cost_history.len(), 6);
+    }
+
+    #[test]
+    fn test_trainer_learns_xor() {
+        use crate::trainer::Trainer;
+
+        let t_input = Mat::new(&[&[0.0, 0.0], &[0.0, 1.0], &[1.0, 0.0], &[1.0, 1.0]]);
+        let t_output = Mat::new(&[&[0.0], &[1.0], &[1.0], &[0.0]]);
+        let mut nn = NN::new(&[2, 4, 1]);
+        NN::randomize(&mut nn, -1.0, 1.0);
+
+        let mut trainer = Trainer::new(nn, t_input, t_output);
+        let initial = trainer.cost;
+        trainer.fit(2_000, &mut []);
+
+        assert!(trainer.cost < initial);
+        assert_eq!(
+            trainer.best_cost,
+            trainer
+                .cost_history
+                .iter()
+                .cloned()
+                .fold(f32::INFINITY, f32::min)
+        );
+    }
+
+    #[test]
+    fn test_losses_and_optimizers() {
+        use crate::dataset;
+        use crate::loss::{CrossEntropy, Loss, Mse};
+        use crate::optimizer::{Momentum, Optimizer, Sgd};
+        use crate::trainer::Trainer;
+
+        // The gradients match the slope of the costs
+        let eps = 1e-3;
+        for loss in [&Mse as &dyn Loss, &CrossEntropy] {
+            for (output, target) in [(0.3, 1.0), (0.8, 0.0), (0.5, 0.5)] {
+                let slope = (loss.cost(output + eps, target) - loss.cost(output - eps, target))
+                    / (2.0 * eps);
+                assert!((loss.gradient(output, target) - slope).abs() < 1e-2);
+            }
+        }
+        assert!(CrossEntropy.cost(1.0, 0.0).is_finite());
+
+        let t_input = Mat::new(&[&[0.0, 0.0], &[0.0, 1.0], &[1.0, 0.0], &[1.0, 1.0]]);
+        let t_output = Mat::new(&[&[0.0], &[1.0], &[1.0], &[0.0]]);
+        let mut nn = NN::new(&[2, 2, 1]);
+        NN::randomize_seeded(&mut nn, -1.0, 1.0, 3);
+        let mut g = nn.clone();
+        NN::backprop(&mut nn.clone(), &mut g, &t_input, &t_output);
+
+        // Momentum's first step is plain gradient descent, the second one
+        // goes further along the same gradient
+        let mut sgd = nn.clone();
+        Sgd.update(&mut sgd, &g, 0.5);
+        let mut learned = nn.clone();
+        NN::learn(&mut learned, &g, 0.5);
+        assert_eq!(sgd.weights[0].data, learned.weights[0].data);
+
+        let mut momentum = Momentum::new(0.9);
+        let mut moved = nn.clone();
+        momentum.update(&mut moved, &g, 0.5);
+        assert_eq!(moved.weights[0].data, learned.weights[0].data);
+        momentum.update(&mut moved, &g, 0.5);
+        let expected = nn.weights[0].data[0][0] - 0.5 * g.weights[0].data[0][0] * 2.9;
+        assert!((moved.weights[0].data[0][0] - expected).abs() < 1e-5);
+
+        // The trainer measures and trains on the loss it's given
+        let mut trainer = Trainer::new(nn, t_input, t_output);
+        trainer.optimizer = Box::new(Momentum::new(0.5));
+        trainer.set_loss(Box::new(CrossEntropy));
+        let initial = dataset::loss_cost(&trainer.nn, &trainer.data, &CrossEntropy);
+        assert_eq!(trainer.cost, initial);
+        assert_eq!(trainer.cost_history, vec![initial]);
+        trainer.fit(200, &mut []);
+        assert_eq!(
+            trainer.cost,
+            dataset::loss_cost(&trainer.nn, &trainer.data, &CrossEntropy)
+        );
+        assert!(trainer.cost < initial);
+    }
+
+    #[test]
+    fn test_validation_monitor_without_rows() {
+        use crate::dataset::InMemory;
+        use crate::trainer::{EarlyStopping, Monitor, Trainer};
+
+        let t_input = Mat::new(&[&[0.0], &[1.0]]);
+        let t_output = Mat::new(&[&[1.0], &[0.0]]);
+        let mut trainer = Trainer::new(NN::new(&[1, 1]), t_input, t_output);
+        let empty = Mat {
+            rows: 0,
+            cols: 1,
+            data: Vec::new(),
+        };
+        trainer.set_validation(Box::new(InMemory::new(empty.clone(), empty)));
+        trainer.early_stopping = Some(EarlyStopping::new(Monitor::ValidationCost, 3, 0.0));
+
+        // The training cost stands in for the missing validation cost
+        trainer.fit(5, &mut []);
+        assert_eq!(trainer.val_cost, None);
+        assert_eq!(trainer.epoch, 5);
+    }
+
+    #[test]
+    fn test_early_stopping_restores_best() {
+        use crate::schedule::Constant;
+        use crate::trainer::{EarlyStopping, Monitor, Trainer};
+
+        let t_input = Mat::new(&[&[0.0, 0.0], &[0.0, 1.0], &[1.0, 0.0], &[1.0, 1.0]]);
+        let t_output = Mat::new(&[&[0.0], &[1.0], &[1.0], &[0.0]]);
+        let mut nn = NN::new(&[2, 2, 1]);
+        NN::randomize_seeded(&mut nn, -1.0, 1.0, 5);
+
+        // Gradient ascent, so the starting weights are likely the best ones,
+        // but the checks hold whichever epoch turns out best
+        let mut trainer = Trainer::new(nn, t_input, t_output);
+        trainer.schedule = Box::new(Constant::new(-1.0));
+        trainer.early_stopping = Some(EarlyStopping::new(Monitor::TrainCost, 3, 0.0));
+        trainer.fit(100, &mut []);
+
+        // The history starts with the cost before the first epoch
+        let (best_epoch, best) = trainer.cost_history.iter().cloned().enumerate().fold(
+            (0, f32::INFINITY),
+            |best, (i, cost)| {
+                if cost < best.1 {
+                    (i, cost)
+                } else {
+                    best
+                }
+            },
+        );
+        assert!(trainer.stopped_early);
+        assert_eq!(trainer.epoch, best_epoch + 3);
+        let early_stopping = trainer.early_stopping.as_ref().unwrap();
+        assert_eq!(early_stopping.best_epoch(), best_epoch);
+        assert_eq!(early_stopping.best(), best);
+        assert_eq!(trainer.cost, best);
+    }
+
+    #[test]
+    fn test_split_holdout_and_k_fold() {
+        use crate::split::{holdout, k_fold};
+
+        let input = Mat {
+            rows: 10,
+            cols: 1,
+            data: (0..10).map(|i| vec![i as f32]).collect(),
+        };
+        let output = input.clone();
+
+        let split = holdout(&input, &output, 0.3, 42);
+        assert_eq!(split.train_input.rows, 7);
+        assert_eq!(split.holdout_input.rows, 3);
+        assert_eq!(split.train_input.data, split.train_output.data);
+        assert_eq!(
+            holdout(&input, &output, 0.3, 42).holdout_input.data,
+            split.holdout_input.data
+        );
+
+        let folds = k_fold(&input, &output, 5, 1);
+        assert_eq!(folds.len(), 5);
+        let mut seen: Vec<f32> = folds
+            .iter()
+            .flat_map(|fold| fold.holdout_input.data.iter().map(|row| row[0]))
+            .collect();
+        seen.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        assert_eq!(seen, (0..10).map(|i| i as f32).collect::<Vec<_>>());
+        assert!(folds.iter().all(|fold| fold.train_input.rows == 8));
+    }
+
+    #[test]
+    fn test_split_stratified() {
+        use crate::split::{class_of, stratified};
+
+        let input = Mat {
+            rows: 12,
+            cols: 1,
+            data: (0..12).map(|i| vec![i as f32]).collect(),
+        };
+        // 8 rows of class 0 and 4 of class 1
+        let output = Mat {
+            rows: 12,
+            cols: 1,
+            data: (0..12).map(|i| vec![(i % 3 == 0) as i32 as f32]).collect(),
+        };
+
+        let split = stratified(&input, &output, 0.5, 7);
+        let ones = |m: &Mat| m.data.iter().filter(|row| class_of(row) == 1).count();
+        assert_eq!(split.holdout_input.rows, 6);
+        assert_eq!(ones(&split.holdout_output), 2);
+        assert_eq!(ones(&split.train_output), 2);
+    }
+
+    #[test]
+    fn test_trainer_validation_cost() {
+        use crate::dataset::InMemory;
+        use crate::trainer::Trainer;
+
+        let t_input = Mat::new(&[&[0.0, 0.0], &[0.0, 1.0], &[1.0, 0.0], &[1.0, 1.0]]);
+        let t_output = Mat::new(&[&[0.0], &[1.0], &[1.0], &[0.0]]);
+        let mut nn = NN::new(&[2, 2, 1]);
+        NN::randomize(&mut nn, -1.0, 1.0);
+
+        let mut trainer = Trainer::new(nn, t_input.clone(), t_output.clone());
+        trainer.set_validation(Box::new(InMemory::new(t_input, t_output)));
+        assert_eq!(trainer.val_cost, Some(trainer.cost));
+
+        trainer.fit(10, &mut []);
+        assert_eq!(trainer.val_cost_history.len(), trainer.cost_history.len());
+        assert_eq!(trainer.val_cost, Some(trainer.cost));
+    }
+
+    // A network whose output is just its single input, so predictions are easy to control
+    fn passthrough(inputs: usize) -> NN {
+        let mut nn = NN::new(&[inputs, inputs]);
+        for i in 0..inputs {
+            nn.weights[0].data[i][i] = 1.0;
+        }
+        nn
+    }
+
+    #[test]
+    fn test_metrics_binary_classification() {
+        use crate::metrics::Classification;
+
+        let nn = passthrough(1);
+        // sigmoid(x) > 0.5 exactly when x > 0
+        let input = Mat::new(&[&[3.0], &[1.0], &[-1.0], &[2.0], &[-2.0], &[-3.0]]);
+        let labels = Mat::new(&[&[1.0], &[1.0], &[1.0], &[0.0], &[0.0], &[0.0]]);
+
+        let report = Classification::evaluate(&nn, &input, &labels);
+
+        assert_eq!(report.confusion, vec![vec![2, 1], vec![1, 2]]);
+        assert!((report.accuracy() - 4.0 / 6.0).abs() < 1e-6);
+        assert!((report.precision(1) - 2.0 / 3.0).abs() < 1e-6);
+        assert!((report.recall(1) - 2.0 / 3.0).abs() < 1e-6);
+        assert!((report.roc_auc.unwrap() - 7.0 / 9.0).abs() < 1e-6);
+        assert!(report.log_loss > 0.0);
+        assert!(report.to_string().contains("accuracy"));
+    }
+
+    #[test]
+    fn test_metrics_multiclass_classification() {
+        use crate::metrics::Classification;
+
+        let nn = passthrough(3);
+        let input = Mat::new(&[
+            &[5.0, 0.0, 0.0],
+            &[0.0, 5.0, 0.0],
+            &[0.0, 0.0, 5.0],
+            &[5.0, 0.0, 0.0],
+        ]);
+        let labels = Mat::new(&[
+            &[1.0, 0.0, 0.0],
+            &[0.0, 1.0, 0.0],
+            &[0.0, 0.0, 1.0],
+            &[0.0, 1.0, 0.0],
+        ]);
+
+        let report = Classification::evaluate(&nn, &input, &labels);
+
+        assert_eq!(report.classes, 3);
+        assert_eq!(report.confusion[1], vec![1, 1, 0]);
+        assert_eq!(report.accuracy(), 0.75);
+        assert_eq!(report.micro_f1(), 0.75);
+        assert_eq!(report.precision(0), 0.5);
+        assert_eq!(report.recall(1), 0.5);
+        assert!((report.macro_f1() - (2.0 / 3.0 + 2.0 / 3.0 + 1.0) / 3.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_metrics_regression() {
+        use crate::metrics::{Regression, Residuals};
+
+        let nn = passthrough(1);
+        // predictions are 0.5, sigmoid(2) and sigmoid(-2)
+        let input = Mat::new(&[&[0.0], &[2.0], &[-2.0]]);
+        let targets = Mat::new(&[&[0.5], &[1.0], &[0.0]]);
+
+        let residuals = Residuals::new(&nn, &input, &targets);
+        let report = Regression::from_residuals(&residuals);
+
+        let e = 1.0 - sigmoidf(2.0);
+        assert!((report.mae - 2.0 * e / 3.0).abs() < 1e-6);
+        assert!((report.rmse - (2.0 * e * e / 3.0).sqrt()).abs() < 1e-6);
+        assert!((report.max_error - e).abs() < 1e-6);
+        // the zero target is skipped
+        assert!((report.mape - 100.0 * e / 2.0).abs() < 1e-4);
+        assert!((report.r2 - (1.0 - 2.0 * e * e / 0.5)).abs() < 1e-6);
+
+        let mut csv = Vec::new();
+        residuals.write_csv(&mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[0], "input_0,target_0,prediction_0,error_0");
+        assert_eq!(lines[1], "0,0.5,0.5,0");
+    }
+
+    #[test]
+    fn test_dataset_batches() {
+        use crate::dataset::{Dataset, InMemory, Lazy};
+
+        let lazy = Lazy::new(5, 1, 1, |i| (vec![i as f32], vec![2.0 * i as f32]));
+        let batches: Vec<(Mat, Mat)> = lazy.batches(2).collect();
+        assert_eq!(batches.len(), 3);
+        assert_eq!(batches[1].0.data, vec![vec![2.0], vec![3.0]]);
+        assert_eq!(batches[2].1.data, vec![vec![8.0]]);
+
+        let (input, output) = lazy.batch(0, 5);
+        let memory = InMemory::new(input, output);
+        let lazy_rows: Vec<(Mat, Mat)> = lazy.batches(3).collect();
+        let memory_rows: Vec<(Mat, Mat)> = memory.batches(3).collect();
+        for (a, b) in lazy_rows.iter().zip(&memory_rows) {
+            assert_eq!(a.0.data, b.0.data);
+            assert_eq!(a.1.data, b.1.data);
+        }
+    }
+
+    #[test]
+    fn test_dataset_streamed() {
+        use crate::dataset::{Dataset, Streamed};
+
+        let path =
+            std::env::temp_dir().join(format!("nn-rust-streamed-{}.txt", std::process::id()));
+        std::fs::write(&path, "0 0 0\n0,1,1\n\n1 0 1\n1 1 0\n").unwrap();
+
+        let data = Streamed::open(&path, 2, 1).unwrap();
+        assert_eq!(data.len(), 4);
+        assert_eq!(data.get(2), (vec![1.0, 0.0], vec![1.0]));
+        assert_eq!(data.get(1), (vec![0.0, 1.0], vec![1.0]));
+
+        let nn = NN::new(&[2, 1]);
+        let (input, output) = data.batch(0, 4);
+        assert_eq!(
+            crate::dataset::cost(&nn, &data),
+            NN::cost(&nn, &input, &output)
+        );
+
+        // Bad lines are caught when opening rather than mid-training
+        std::fs::write(&path, "0 0 0\n0 1\n").unwrap();
+        let err = Streamed::open(&path, 2, 1).err().unwrap();
+        assert!(err.to_string().starts_with("line 2:"));
+        std::fs::write(&path, "0 0 0\n0 x 1\n").unwrap();
+        assert!(Streamed::open(&path, 2, 1).is_err());
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_csv_columns_and_one_hot() {
+        use crate::csv::{parse, Column, CsvOptions};
+
+        let text = "x,color,ignored,y\n1,red,9,0\n2,\"blue\",9,1\n\n3,red,9,1\n";
+        let options = CsvOptions {
+            inputs: vec![Column::from("x"), Column::from(1)],
+            targets: vec![Column::from("y")],
+            ..CsvOptions::default()
+        };
+
+        let data = parse(text, &options).unwrap();
+        assert_eq!(data.input_names, vec!["x", "color=blue", "color=red"]);
+        assert_eq!(data.output_names, vec!["y"]);
+        assert_eq!(
+            data.t_input.data,
+            vec![
+                vec![1.0, 0.0, 1.0],
+                vec![2.0, 1.0, 0.0],
+                vec![3.0, 0.0, 1.0]
+            ]
+        );
+        assert_eq!(data.t_output.data, vec![vec![0.0], vec![1.0], vec![1.0]]);
+
+        let options = CsvOptions {
+            targets: vec![Column::from("z")],
+            ..CsvOptions::default()
+        };
+        assert!(parse(text, &options).is_err());
+    }
+
+    #[test]
+    fn test_csv_schema() {
+        use crate::csv::{field_count, parse, parse_with_schema, CsvError, CsvOptions, Schema};
+
+        let options = CsvOptions::default();
+        let data = parse("x,color,y\n1,red,0\n2,blue,1\n", &options).unwrap();
+        assert_eq!(data.schema.input_width(), 3);
+        let schema = Schema::from_json(&data.schema.to_json()).unwrap();
+        assert_eq!(schema, data.schema);
+
+        // Categories come from the schema, not the new file
+        let other = parse_with_schema("x,color,y\n3,red,1\n4,green,0\n", &options, &schema);
+        assert_eq!(
+            other.unwrap().t_input.data,
+            vec![vec![3.0, 0.0, 1.0], vec![4.0, 0.0, 0.0]]
+        );
+
+        // Without targets every column is an input
+        let inputs = Schema {
+            targets: Vec::new(),
+            ..schema.clone()
+        };
+        let other = parse_with_schema("x,color\n5,blue\n", &options, &inputs).unwrap();
+        assert_eq!(other.t_input.data, vec![vec![5.0, 1.0, 0.0]]);
+        assert_eq!(other.t_output.cols, 0);
+
+        match parse_with_schema("x,color,y\nfive,red,1\n", &options, &schema) {
+            Err(CsvError::NotANumber { line, column }) => {
+                assert_eq!((line, column.as_str()), (2, "x"))
+            }
+            other => panic!("expected a not a number error, got {:?}", other),
+        }
+
+        assert_eq!(field_count("\"a,b\",c\n1,2\n", &options).unwrap(), 2);
+    }
+
+    #[test]
+    fn test_csv_missing_values() {
+        use crate::csv::{parse, CsvError, CsvOptions, Missing};
+
+        let text = "1;2;0\n;4;1\n5;NA;1\n";
+        let mut options = CsvOptions {
+            has_header: false,
+            delimiter: ';',
+            ..CsvOptions::default()
+        };
+
+        match parse(text, &options) {
+            Err(CsvError::MissingValue { line, .. }) => assert_eq!(line, 2),
+            other => panic!("expected a missing value error, got {:?}", other),
+        }
+
+        options.missing = Missing::SkipRow;
+        assert_eq!(parse(text, &options).unwrap().t_input.rows, 1);
+
+        options.missing = Missing::Mean;
+        let data = parse(text, &options).unwrap();
+        assert_eq!(data.t_input.data[1], vec![3.0, 4.0]);
+        assert_eq!(data.t_input.data[2], vec![5.0, 3.0]);
+
+        options.missing = Missing::Fill(-1.0);
+        assert_eq!(parse(text, &options).unwrap().t_input.data[1][0], -1.0);
+    }
+
+    // Header of an IDX file holding unsigned bytes with the given dimensions
+    fn idx_header(dims: &[u32]) -> Vec<u8> {
+        let mut bytes = vec![0, 0, 0x08, dims.len() as u8];
+        for dim in dims {
+            bytes.extend_from_slice(&dim.to_be_bytes());
+        }
+        bytes
+    }
+
+    #[test]
+    fn test_idx_mnist() {
+        use crate::idx::load_mnist;
+
+        let dir = std::env::temp_dir();
+        let images_path = dir.join(format!("nn-rust-images-{}.idx", std::process::id()));
+        let labels_path = dir.join(format!("nn-rust-labels-{}.idx", std::process::id()));
+
+        // Two 2x2 images and their labels
+        let mut images = idx_header(&[2, 2, 2]);
+        images.extend_from_slice(&[0, 255, 51, 102, 255, 0, 0, 255]);
+        let mut labels = idx_header(&[2]);
+        labels.extend_from_slice(&[3, 9]);
+        std::fs::write(&images_path, images).unwrap();
+        std::fs::write(&labels_path, labels).unwrap();
+
+        let (input, output) = load_mnist(&images_path, &labels_path).unwrap();
+        let idx = crate::idx::Idx::load(&images_path).unwrap();
+        assert_eq!(idx.image_dims(), Some((2, 2, 1)));
+        assert_eq!((input.rows, input.cols), (2, 4));
+        assert_eq!(input.data[0], vec![0.0, 1.0, 0.2, 0.4]);
+        assert_eq!((output.rows, output.cols), (2, 10));
+        assert_eq!(output.data[0][3], 1.0);
+        assert_eq!(output.data[1][9], 1.0);
+        assert_eq!(output.data[1].iter().sum::<f32>(), 1.0);
+
+        std::fs::remove_file(&images_path).unwrap();
+        std::fs::remove_file(&labels_path).unwrap();
+    }
+
+    #[test]
+    fn test_idx_errors() {
+        use crate::idx::{Idx, IdxError};
+
+        let mut truncated = idx_header(&[3]);
+        truncated.extend_from_slice(&[1, 2]);
+        assert!(matches!(
+            Idx::read(&mut truncated.as_slice()),
+            Err(IdxError::Truncated)
+        ));
+
+        // A header promising more than the file holds or than fits in memory
+        let huge = idx_header(&[u32::MAX, u32::MAX]);
+        assert!(matches!(
+            Idx::read(&mut huge.as_slice()),
+            Err(IdxError::Truncated)
+        ));
+        let huge = idx_header(&[u32::MAX, u32::MAX, 16]);
+        assert!(matches!(
+            Idx::read(&mut huge.as_slice()),
+            Err(IdxError::TooLarge)
+        ));
+
+        let bad = [1u8, 0, 8, 1, 0, 0, 0, 0];
+        assert!(matches!(
+            Idx::read(&mut bad.as_slice()),
+            Err(IdxError::BadMagic(_))
+        ));
+
+        let mut labels = idx_header(&[1]);
+        labels.push(12);
+        let labels = Idx::read(&mut labels.as_slice()).unwrap();
+        assert!(matches!(labels.to_one_hot(10), Err(IdxError::BadLabel(_))));
+
+        // An images file isn't a labels file
+        let mut images = idx_header(&[1, 1, 2]);
+        images.extend_from_slice(&[1, 2]);
+        let images = Idx::read(&mut images.as_slice()).unwrap();
+        assert!(matches!(images.to_one_hot(10), Err(IdxError::BadDims(_))));
+
+        // Items without any values
+        let empty = Idx::read(&mut idx_header(&[3, 0]).as_slice()).unwrap();
+        assert!(matches!(empty.to_inputs(), Err(IdxError::BadDims(_))));
+    }
+
+    #[test]
+    fn test_image_pnm() {
+        use crate::image::{Image, ImageError};
+
+        let ascii = b"P2\n# a comment\n2 2\n4\n0 1\n2 4\n";
+        let image = Image::decode(ascii).unwrap();
+        assert_eq!((image.width, image.height, image.channels), (2, 2, 1));
+        assert_eq!(image.pixels, vec![0.0, 0.25, 0.5, 1.0]);
+
+        let mut binary = b"P6\n1 2\n255\n".to_vec();
+        binary.extend_from_slice(&[255, 0, 0, 0, 51, 255]);
+        let image = Image::decode(&binary).unwrap();
+        assert_eq!(image.channels, 3);
+        assert_eq!(image.get(0, 1), &[0.0, 0.2, 1.0]);
+
+        let mut written = Vec::new();
+        image.write_pnm(&mut written).unwrap();
+        assert_eq!(written, binary);
+
+        // Sizes the file can't hold fail before anything is allocated
+        for header in [
+            &b"P5 4000000000 4000000000 255 "[..],
+            b"P6 99999 99999 255 \0",
+        ] {
+            assert!(matches!(Image::decode(header), Err(ImageError::Pnm(_))));
+        }
+
+        // Values above the max value
+        for bad in [&b"P2 1 1 4 5"[..], b"P5 1 1 4 \x05"] {
+            assert!(matches!(Image::decode(bad), Err(ImageError::Pnm(_))));
+        }
+
+        let path = std::env::temp_dir().join("nn_rust_test_image.bmp");
+        assert!(matches!(image.save(&path), Err(ImageError::Unsupported)));
+        assert!(!path.exists());
+
+        // A grayscale image saved as PPM turns into RGB
+        let gray = Image::decode(ascii).unwrap();
+        let path = std::env::temp_dir().join(format!("nn-rust-gray-{}.ppm", std::process::id()));
+        gray.save(&path).unwrap();
+        let saved = Image::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(saved.channels, 3);
+        assert_eq!(saved.get(1, 1), &[1.0, 1.0, 1.0]);
+    }
+
+    #[test]
+    fn test_image_png_round_trip() {
+        use crate::image::Image;
+
+        let mut image = Image::new(3, 2, 3).unwrap();
+        for (i, pixel) in image.pixels.iter_mut().enumerate() {
+            *pixel = (i * 10) as f32 / 255.0;
+        }
+
+        let mut png = Vec::new();
+        image.write_png(&mut png).unwrap();
+        let decoded = Image::decode(&png).unwrap();
+        assert_eq!((decoded.width, decoded.height, decoded.channels), (3, 2, 3));
+        for (a, b) in decoded.pixels.iter().zip(&image.pixels) {
+            assert!((a - b).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn test_image_training_rows() {
+        use crate::image::{Image, ImageError};
+
+        let image = Image::decode(b"P2 3 2 2 0 1 2 2 1 0").unwrap();
+        let (t_input, t_output) = image.to_training();
+        assert_eq!(t_input.rows, 6);
+        assert_eq!(t_input.data[5], vec![1.0, 1.0]);
+        assert_eq!(t_input.data[1], vec![0.5, 0.0]);
+        assert_eq!(t_output.data[3], vec![1.0]);
+
+        let nn = NN::new(&[2, 3, 1]);
+        let rendered = Image::from_nn(&nn, 5, 4).unwrap();
+        assert_eq!(
+            (rendered.width, rendered.height, rendered.channels),
+            (5, 4, 1)
+        );
+        // an all-zero network outputs sigmoid(0) everywhere
+        assert!(rendered.pixels.iter().all(|v| *v == 0.5));
+        assert_eq!(rendered.to_rgba8().len(), 5 * 4 * 4);
+
+        assert!(matches!(
+            Image::from_nn(&NN::new(&[2, 2]), 5, 4),
+            Err(ImageError::Channels(2))
+        ));
+    }
+
+    #[test]
+    fn test_image_interpolation_rows() {
+        use crate::image::{interpolation_training, Image};
+
+        let black = Image::new(2, 2, 1).unwrap();
+        let mut white = Image::new(2, 2, 1).unwrap();
+        white.pixels = vec![1.0; 4];
+        let grey = Image::new(1, 1, 1).unwrap();
+
+        let (t_input, t_output) = interpolation_training(&[black, grey, white]);
+        assert_eq!((t_input.rows, t_input.cols), (9, 3));
+        assert_eq!(t_input.data[0], vec![0.0, 0.0, 0.0]);
+        assert_eq!(t_input.data[4], vec![0.0, 0.0, 0.5]);
+        assert_eq!(t_input.data[8], vec![1.0, 1.0, 1.0]);
+        assert_eq!(t_output.data[8], vec![1.0]);
+
+        let nn = NN::new(&[3, 1]);
+        let frame = Image::from_nn_blended(&nn, 3, 3, &[0.25]).unwrap();
+        assert_eq!(frame.pixels.len(), 9);
+    }
+
+    #[test]
+    fn test_preprocess_scalers_round_trip() {
+        use crate::preprocess::{Pipeline, Transform};
+
+        let data = Mat::new(&[&[1.0, 10.0], &[2.0, 10.0], &[3.0, 40.0], &[4.0, 20.0]]);
+
+        let mut min_max = Transform::min_max();
+        min_max.fit(&data);
+        let scaled = min_max.transform(&data);
+        assert_eq!(scaled.data[0], vec![0.0, 0.0]);
+        assert_eq!(scaled.data[2], vec![2.0 / 3.0, 1.0]);
+
+        let mut standard = Transform::standard();
+        standard.fit(&data);
+        let scaled = standard.transform(&data);
+        let mean: f32 = scaled.data.iter().map(|row| row[0]).sum::<f32>() / 4.0;
+        let var: f32 = scaled.data.iter().map(|row| row[0] * row[0]).sum::<f32>() / 4.0;
+        assert!(mean.abs() < 1e-6 && (var - 1.0).abs() < 1e-5);
+
+        let mut robust = Transform::robust();
+        robust.fit(&data);
+        // median 2.5 and quartiles 1.75, 3.25 in the first column
+        assert_eq!(robust.transform(&data).data[0][0], -1.0);
+
+        let mut pipeline = Pipeline::new(vec![Transform::robust(), Transform::min_max()]);
+        let scaled = pipeline.fit_transform(&data);
+        let restored = pipeline.inverse(&scaled);
+        for (a, b) in restored
+            .data
+            .iter()
+            .flatten()
+            .zip(data.data.iter().flatten())
+        {
+            assert!((a - b).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn test_preprocess_encoders_and_polynomial() {
+        use crate::preprocess::{Pipeline, Transform};
+
+        let data = Mat::new(&[&[2.0, 7.0], &[3.0, 5.0], &[2.0, 9.0]]);
+
+        let mut one_hot = Transform::one_hot(1);
+        one_hot.fit(&data);
+        let encoded = one_hot.transform(&data);
+        assert_eq!(encoded.cols, 4);
+        assert_eq!(encoded.data[0], vec![2.0, 0.0, 1.0, 0.0]);
+        assert_eq!(one_hot.inverse(&encoded).data, data.data);
+
+        let mut label = Transform::label(0);
+        label.fit(&data);
+        let encoded = label.transform(&data);
+        assert_eq!(encoded.data[1], vec![1.0, 5.0]);
+        assert_eq!(label.inverse(&encoded).data, data.data);
+        // unseen values go to the closest class
+        let unseen = label.transform(&Mat::new(&[&[2.9, 0.0], &[-1.0, 0.0], &[2.5, 0.0]]));
+        assert_eq!(
+            unseen.data.iter().map(|row| row[0]).collect::<Vec<_>>(),
+            vec![1.0, 0.0, 0.0]
+        );
+
+        let mut polynomial = Pipeline::new(vec![Transform::polynomial(2)]);
+        let expanded = polynomial.fit_transform(&data);
+        // x, y, x*x, x*y, y*y
+        assert_eq!(expanded.data[0], vec![2.0, 7.0, 4.0, 14.0, 49.0]);
+        assert_eq!(polynomial.inverse(&expanded).data, data.data);
+
+        assert_eq!(polynomial.input_cols(5), Some(2));
+        assert_eq!(polynomial.input_cols(4), None);
+        let encoders = Pipeline::new(vec![one_hot, label]);
+        assert_eq!(encoders.input_cols(4), Some(2));
+        assert_eq!(encoders.input_cols(2), None);
+
+        let json = polynomial.to_json();
+        assert_eq!(Pipeline::from_json(&json).unwrap(), polynomial);
+        assert!(Pipeline::from_json(&serde_json::json!([{"type": "log"}])).is_err());
+
+        // No rows keep their width
+        let none = Mat::slice_rows(&data, 0, 0);
+        assert_eq!(polynomial.transform(&none).cols, 5);
+        let encoded_none = encoders.transform(&none);
+        assert_eq!(encoded_none.cols, 4);
+        assert_eq!(encoders.inverse(&encoded_none).cols, 2);
+
+        // An unfitted label transform leaves the values alone
+        assert_eq!(Transform::label(0).inverse(&encoded).data, encoded.data);
+    }
+
+    #[test]
+    fn test_model_save_and_predict() {
+        use crate::model::Model;
+        use crate::preprocess::{Pipeline, Transform};
+
+        let t_input = Mat::new(&[&[100.0], &[300.0]]);
+        let t_output = Mat::new(&[&[-5.0], &[5.0]]);
+
+        let mut model = Model::new(passthrough(1));
+        model.input = Pipeline::new(vec![Transform::min_max()]);
+        model.input.fit(&t_input);
+        model.output = Pipeline::new(vec![Transform::min_max()]);
+        model.output.fit(&t_output);
+        NN::randomize(&mut model.nn, -1.0, 1.0);
+
+        let path = std::env::temp_dir().join(format!("model-{}.json", std::process::id()));
+        model.save(&path).unwrap();
+        let loaded = Model::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(loaded.nn.weights[0].data, model.nn.weights[0].data);
+        assert_eq!(loaded.input, model.input);
+        let input = Mat::new(&[&[200.0], &[0.0]]);
+        let expected = model.predict(&input);
+        assert_eq!(loaded.predict(&input).data, expected.data);
+        // outputs come back in the targets' units
+        assert!(expected
+            .data
+            .iter()
+            .all(|row| (-5.0..=5.0).contains(&row[0])));
+
+        // pipelines that don't fit the layers are refused when loading
+        let mut json = model.to_json();
+        json["input"] = Pipeline::new(vec![Transform::one_hot(0)]).to_json();
+        assert!(Model::from_json(&json).is_err());
+        json["input"] = serde_json::json!([]);
+        json["columns"] = crate::csv::Schema::numeric(2, 1).to_json();
+        assert!(Model::from_json(&json).is_err());
+    }
+
+    #[test]
+    fn test_augment_flip_and_identity() {
+        use crate::augment::Augmentations;
+        use rand::{rngs::StdRng, SeedableRng};
+
+        let mut rng = StdRng::seed_from_u64(0);
+        // 3x2 grayscale
+        let pixels = [0.0, 0.1, 0.2, 0.3, 0.4, 0.5];
+
+        let none = Augmentations::default();
+        assert_eq!(none.apply(&pixels, 3, 2, 1, &mut rng), pixels);
+
+        let flip = Augmentations {
+            flip_horizontal: true,
+            ..Augmentations::default()
+        };
+        let flips: Vec<Vec<f32>> = (0..20)
+            .map(|_| flip.apply(&pixels, 3, 2, 1, &mut rng))
+            .collect();
+        assert!(flips.contains(&pixels.to_vec()));
+        assert!(flips.contains(&vec![0.2, 0.1, 0.0, 0.5, 0.4, 0.3]));
+    }
+
+    #[test]
+    fn test_augmented_dataset_is_seeded() {
+        use crate::augment::{Augmentations, Augmented};
+        use crate::dataset::{Dataset, InMemory};
+
+        let input = Mat {
+            rows: 4,
+            cols: 16,
+            data: (0..4)
+                .map(|i| (0..16).map(|j| ((i + j) % 5) as f32 / 4.0).collect())
+                .collect(),
+        };
+        let output = Mat::new(&[&[0.0], &[1.0], &[0.0], &[1.0]]);
+        let augmentations = Augmentations {
+            min_crop: 0.8,
+            max_rotation: 15.0,
+            max_translation: 0.1,
+            brightness: 0.1,
+            contrast: 0.2,
+            noise: 0.05,
+            ..Augmentations::default()
+        };
+        let data = || {
+            Augmented::new(
+                InMemory::new(input.clone(), output.clone()),
+                (4, 4, 1),
+                augmentations.clone(),
+                7,
+            )
+        };
+
+        let (a, b) = (data(), data());
+        let batches_a: Vec<(Mat, Mat)> = a.batches(3).collect();
+        let batches_b: Vec<(Mat, Mat)> = b.batches(3).collect();
+        for ((xa, ya), (xb, yb)) in batches_a.iter().zip(&batches_b) {
+            assert_eq!(xa.data, xb.data);
+            assert_eq!(ya.data, yb.data);
+        }
+        assert_eq!(batches_a[1].1.data, vec![vec![1.0]]);
+
+        let (first, _) = a.batch(0, 4);
+        assert_ne!(first.data, input.data);
+        assert_ne!(first.data, batches_a[0].0.data[..3].to_vec());
+        assert!(first.data.iter().flatten().all(|v| (0.0..=1.0).contains(v)));
+
+        // The cost ignores the augmentations
+        let mut nn = NN::new(&[16, 1]);
+        NN::randomize(&mut nn, -1.0, 1.0);
+        assert_eq!(
+            crate::dataset::cost(&nn, &a),
+            NN::cost(&nn, &input, &output)
+        );
+    }
+
+    #[test]
+    fn test_generate_truth_tables() {
+        use crate::generate::{adder, and, multiplier, nand, or, xor};
+
+        let (t_input, t_output) = xor(3);
+        assert_eq!(t_input.rows, 8);
+        assert_eq!(t_input.data[6], vec![1.0, 1.0, 0.0]);
+        assert_eq!(t_output.data[6], vec![0.0]);
+        assert_eq!(t_output.data[7], vec![1.0]);
+
+        assert_eq!(
+            and(2).1.data,
+            vec![vec![0.0], vec![0.0], vec![0.0], vec![1.0]]
+        );
+        assert_eq!(
+            or(2).1.data,
+            vec![vec![0.0], vec![1.0], vec![1.0], vec![1.0]]
+        );
+        assert_eq!(
+            nand(2).1.data,
+            vec![vec![1.0], vec![1.0], vec![1.0], vec![0.0]]
+        );
+
+        // 3 + 2 = 5 and 3 * 2 = 6 with 2-bit operands
+        let (t_input, t_output) = adder(2);
+        assert_eq!((t_input.rows, t_output.cols), (16, 3));
+        assert_eq!(t_input.data[14], vec![1.0, 1.0, 1.0, 0.0]);
+        assert_eq!(t_output.data[14], vec![1.0, 0.0, 1.0]);
+        let (_, t_output) = multiplier(2);
+        assert_eq!(t_output.data[14], vec![0.0, 1.0, 1.0, 0.0]);
+    }
+
+    #[test]
+    fn test_generate_point_sets() {
+        use crate::generate::{blobs, circles, moons, opposite, sine, spirals};
+
+        for (t_input, t_output) in [
+            spirals(200, 1.5, 0.0, 1),
+            circles(200, 0.5, 0.0, 1),
+            moons(200, 0.0, 1),
+        ] {
+            assert_eq!((t_input.rows, t_input.cols, t_output.cols), (200, 2, 1));
+            assert_eq!(
+                t_output.data.iter().filter(|row| row[0] == 1.0).count(),
+                100
+            );
+            assert!(t_input
+                .data
+                .iter()
+                .flatten()
+                .all(|v| (0.0..=1.0).contains(v)));
+        }
+
+        let (t_input, t_output) = blobs(90, 3, 0.05, 4);
+        assert_eq!((t_input.rows, t_output.cols), (90, 3));
+        assert_eq!(t_output.data[4], vec![0.0, 1.0, 0.0]);
+        assert_eq!(blobs(90, 3, 0.05, 4).0.data, t_input.data);
+        assert_ne!(blobs(90, 3, 0.05, 5).0.data, t_input.data);
+
+        let (t_input, t_output) = opposite(11);
+        assert_eq!(t_input.rows, 11);
+        assert_eq!((t_input.data[0][0], t_output.data[0][0]), (1.0, 0.0));
+        assert_eq!((t_input.data[10][0], t_output.data[10][0]), (0.0, 1.0));
+
+        let (t_input, t_output) = sine(50, 0.0, 2);
+        assert!(t_input.data.windows(2).all(|w| w[0][0] <= w[1][0]));
+        assert!(t_output
+            .data
+            .iter()
+            .all(|row| (0.1..=0.9).contains(&row[0])));
+    }
+
+    #[test]
+    fn test_config_parse() {
+        use crate::config::{Config, ConfigError};
+        use crate::preprocess::Transform;
+
+        let config = Config::parse(
+            r#"{"hidden": [8, 4], "learning_rate": 0.5, "epochs": 10, "batch_size": 16,
+                "dataset": "moons", "seed": 3, "activations": ["sigmoid", "sigmoid", "sigmoid"],
+                "validation": 0.25, "output_pipeline": ["min_max"],
+                "input_pipeline": ["standard", {"type": "one_hot", "column": 1}],
+                "augmentation": {"flip_horizontal": true, "max_rotation": 15, "noise": 0.1}}"#,
+        )
+        .unwrap();
+        assert_eq!(config.hidden, Some(vec![8, 4]));
+        assert_eq!((config.learning_rate, config.epochs), (0.5, 10));
+        assert_eq!((config.batch_size, config.seed), (Some(16), Some(3)));
+        assert_eq!(config.dataset.as_deref(), Some("moons"));
+        assert_eq!(config.validation, 0.25);
+        assert_eq!(
+            config.input_pipeline.steps,
+            vec![Transform::standard(), Transform::one_hot(1)]
+        );
+        assert_eq!(config.output_pipeline.steps, vec![Transform::min_max()]);
+        assert!(config.augmentation.flip_horizontal);
+        assert_eq!(
+            (config.augmentation.max_rotation, config.augmentation.noise),
+            (15.0, 0.1)
+        );
+        assert_eq!(Config::parse("{}").unwrap(), Config::default());
+        assert_eq!(
+            Config::parse(&config.to_json().to_string()).unwrap(),
+            config
+        );
+
+        let field = |text: &str| match Config::parse(text) {
+            Err(ConfigError::Field { field, .. }) => field,
+            other => panic!("expected a field error, got {:?}", other),
+        };
+        assert_eq!(field(r#"{"hidden": [4, 0]}"#), "hidden[1]");
+        assert_eq!(field(r#"{"learning_rate": -1}"#), "learning_rate");
+        let config = Config::parse(r#"{"loss": "cross_entropy", "optimizer": "momentum"}"#);
+        assert_eq!(config.unwrap().optimizer, "momentum");
+        assert_eq!(field(r#"{"optimizer": "adam"}"#), "optimizer");
+        assert_eq!(field(r#"{"validation": 1}"#), "validation");
+        assert_eq!(
+            field(r#"{"input_pipeline": ["min_max", "log"]}"#),
+            "input_pipeline[1]"
+        );
+        assert_eq!(
+            field(r#"{"output_pipeline": [{"type": "polynomial", "degree": 0}]}"#),
+            "output_pipeline[0].degree"
+        );
+        assert_eq!(
+            field(r#"{"augmentation": {"min_crop": 0}}"#),
+            "augmentation.min_crop"
+        );
+        assert_eq!(
+            field(r#"{"augmentation": {"flip": true}}"#),
+            "augmentation.flip"
+        );
+        assert_eq!(
+            field(r#"{"activation": ["sigmoid", "relu"]}"#),
+            "activation[1]"
+        );
+        assert_eq!(
+            field(r#"{"hidden": [2], "activations": ["sigmoid", "sigmoid", "sigmoid"]}"#),
+            "activations"
+        );
+        assert_eq!(
+            field(r#"{"hidden": [2], "activation": ["sigmoid", "sigmoid", "sigmoid"]}"#),
+            "activation"
+        );
+        assert_eq!(field(r#"{"learning_rte": 1}"#), "learning_rte");
+        assert!(matches!(Config::parse("{"), Err(ConfigError::Json(_))));
+    }
+
+    #[test]
+    fn test_nn_randomize_seeded() {
+        let mut a = NN::new(&[2, 3, 1]);
+        let mut b = NN::new(&[2, 3, 1]);
+        NN::randomize_seeded(&mut a, -1.0, 1.0, 9);
+        NN::randomize_seeded(&mut b, -1.0, 1.0, 9);
+        assert_eq!(a.weights[0].data, b.weights[0].data);
+        assert_eq!(a.biases[1].data, b.biases[1].data);
+        NN::randomize_seeded(&mut b, -1.0, 1.0, 10);
+        assert_ne!(a.weights[0].data, b.weights[0].data);
+    }
 }
@@ -0,0 +1,330 @@
+use crate::{
+    dataset::{self, Dataset, InMemory},
+    loss::{Loss, Mse},
+    optimizer::{Optimizer, Sgd},
+    schedule::{Constant, Schedule},
+    Mat, NN,
+};
+
+/// Returned by callbacks to tell the [`Trainer`] whether to keep going.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Flow {
+    Continue,
+    Stop,
+}
+
+/// Which cost [`EarlyStopping`] watches.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Monitor {
+    TrainCost,
+    /// Falls back to the training cost while there's no validation cost.
+    ValidationCost,
+}
+
+/// Stops training once the monitored cost hasn't improved by more than
+/// `min_delta` for `patience` epochs. With `restore_best` the trainer rolls
+/// the network back to the best weights seen when training ends, the ones it
+/// started the first epoch with included.
+#[derive(Clone, Debug)]
+pub struct EarlyStopping {
+    pub monitor: Monitor,
+    pub patience: usize,
+    pub min_delta: f32,
+    pub restore_best: bool,
+    best: f32,
+    best_epoch: usize,
+    best_nn: Option<NN>,
+    wait: usize,
+    // set once the weights before the first epoch were taken into account
+    started: bool,
+}
+
+impl EarlyStopping {
+    pub fn new(monitor: Monitor, patience: usize, min_delta: f32) -> EarlyStopping {
+        EarlyStopping {
+            monitor,
+            patience,
+            min_delta,
+            restore_best: true,
+            best: f32::INFINITY,
+            best_epoch: 0,
+            best_nn: None,
+            wait: 0,
+            started: false,
+        }
+    }
+
+    pub fn best(&self) -> f32 {
+        self.best
+    }
+
+    pub fn best_epoch(&self) -> usize {
+        self.best_epoch
+    }
+
+    // The network training starts from is the first candidate for the best
+    fn start(&mut self, epoch: usize, value: f32, nn: &NN) {
+        self.started = true;
+        self.best = value;
+        self.best_epoch = epoch;
+        self.wait = 0;
+        if self.restore_best {
+            self.best_nn = Some(nn.clone());
+        }
+    }
+
+    // returns true when training should stop
+    fn update(&mut self, epoch: usize, value: f32, nn: &NN) -> bool {
+        if value < self.best - self.min_delta {
+            self.best = value;
+            self.best_epoch = epoch;
+            self.wait = 0;
+            if self.restore_best {
+                self.best_nn = Some(nn.clone());
+            }
+        } else {
+            self.wait += 1;
+        }
+
+        self.wait >= self.patience
+    }
+}
+
+/// Hooks into the training loop. Every method has an empty default so
+/// implementors only override what they care about.
+pub trait Callback {
+    /// Called after every mini-batch update, `batch` counting from 0 within
+    /// the current epoch.
+    fn on_batch_end(&mut self, _trainer: &Trainer, _batch: usize) -> Flow {
+        Flow::Continue
+    }
+
+    /// Called once the epoch is done and `trainer.cost` has been updated.
+    fn on_epoch_end(&mut self, _trainer: &Trainer) -> Flow {
+        Flow::Continue
+    }
+
+    /// Called after an epoch that lowered the best cost seen so far.
+    fn on_improvement(&mut self, _trainer: &Trainer) {}
+}
+
+/// Owns everything needed to train a network, so the GUI, tools and tests
+/// all run the same loop. Plain gradient descent on the squared error unless
+/// told otherwise.
+pub struct Trainer {
+    pub nn: NN,
+    pub gradient: NN,
+    pub data: Box<dyn Dataset + Send>,
+    pub schedule: Box<dyn Schedule + Send>,
+    pub optimizer: Box<dyn Optimizer + Send>,
+    // every cost is measured with it, see `set_loss`
+    loss: Box<dyn Loss + Send>,
+    /// Rows per gradient step, 0 trains on the whole set at once.
+    pub batch_size: usize,
+    /// Number of completed epochs.
+    pub epoch: usize,
+    pub learning_rate: f32,
+    pub cost: f32,
+    pub best_cost: f32,
+    pub cost_history: Vec<f32>,
+    /// Held out samples that are never trained on.
+    pub validation: Option<Box<dyn Dataset + Send>>,
+    /// Cost on the validation set, scaled to the size of the training set so
+    /// it can be compared with `cost` directly.
+    pub val_cost: Option<f32>,
+    pub val_cost_history: Vec<f32>,
+    pub early_stopping: Option<EarlyStopping>,
+    /// Set once early stopping has ended training.
+    pub stopped_early: bool,
+}
+
+impl Trainer {
+    pub fn new(nn: NN, t_input: Mat, t_output: Mat) -> Trainer {
+        Self::with_dataset(nn, Box::new(InMemory::new(t_input, t_output)))
+    }
+
+    pub fn with_dataset(nn: NN, data: Box<dyn Dataset + Send>) -> Trainer {
+        assert_eq!(data.input_cols(), nn.activations[0].cols);
+        assert_eq!(data.output_cols(), nn.activations[nn.count - 1].cols);
+        let cost = dataset::cost(&nn, &data);
+
+        Trainer {
+            gradient: nn.clone(),
+            nn,
+            data,
+            schedule: Box::new(Constant::new(1.0)),
+            optimizer: Box::new(Sgd),
+            loss: Box::new(Mse),
+            batch_size: 0,
+            epoch: 0,
+            learning_rate: 1.0,
+            cost,
+            best_cost: cost,
+            cost_history: vec![cost],
+            validation: None,
+            val_cost: None,
+            val_cost_history: Vec::new(),
+            early_stopping: None,
+            stopped_early: false,
+        }
+    }
+
+    /// Runs a single epoch and returns [`Flow::Stop`] if a callback asked to
+    /// stop.
+    pub fn step(&mut self, callbacks: &mut [&mut dyn Callback]) -> Flow {
+        let mut flow = Flow::Continue;
+        // Early stopping can be attached any time before, so it picks up the
+        // starting network here
+        let monitored = self.monitored();
+        if let Some(early_stopping) = &mut self.early_stopping {
+            if !early_stopping.started {
+                early_stopping.start(self.epoch, monitored, &self.nn);
+            }
+        }
+        self.learning_rate = self.schedule.rate(self.epoch, self.cost);
+
+        let batch_size = if self.batch_size == 0 {
+            self.data.len().max(1)
+        } else {
+            self.batch_size
+        };
+
+        for (batch, (input, output)) in self.data.batches(batch_size).enumerate() {
+            NN::loss_backprop(
+                &mut self.nn,
+                &mut self.gradient,
+                &input,
+                &output,
+                &self.loss,
+            );
+            self.optimizer
+                .update(&mut self.nn, &self.gradient, self.learning_rate);
+
+            for callback in callbacks.iter_mut() {
+                if callback.on_batch_end(self, batch) == Flow::Stop {
+                    flow = Flow::Stop;
+                }
+            }
+            if flow == Flow::Stop {
+                break;
+            }
+        }
+
+        self.epoch += 1;
+        self.cost = dataset::loss_cost(&self.nn, &self.data, &self.loss);
+        self.cost_history.push(self.cost);
+        self.validate();
+
+        if self.cost < self.best_cost {
+            self.best_cost = self.cost;
+            for callback in callbacks.iter_mut() {
+                callback.on_improvement(self);
+            }
+        }
+
+        let monitored = self.monitored();
+        if let Some(early_stopping) = &mut self.early_stopping {
+            if early_stopping.update(self.epoch, monitored, &self.nn) {
+                self.stopped_early = true;
+                self.restore_best();
+            }
+        }
+
+        for callback in callbacks.iter_mut() {
+            if callback.on_epoch_end(self) == Flow::Stop {
+                flow = Flow::Stop;
+            }
+        }
+
+        if self.stopped_early {
+            Flow::Stop
+        } else {
+            flow
+        }
+    }
+
+    /// Sets the validation samples and measures the current network on them.
+    pub fn set_validation(&mut self, validation: Box<dyn Dataset + Send>) {
+        assert_eq!(validation.input_cols(), self.data.input_cols());
+        assert_eq!(validation.output_cols(), self.data.output_cols());
+
+        self.validation = Some(validation);
+        self.val_cost_history.clear();
+        self.validate();
+        // The cost early stopping started from may have changed
+        if let Some(early_stopping) = &mut self.early_stopping {
+            early_stopping.started = false;
+        }
+    }
+
+    pub fn loss(&self) -> &dyn Loss {
+        &self.loss
+    }
+
+    /// Switches the loss the network is trained on. The costs measured so
+    /// far aren't comparable any more, so the histories start over.
+    pub fn set_loss(&mut self, loss: Box<dyn Loss + Send>) {
+        self.loss = loss;
+        self.cost = dataset::loss_cost(&self.nn, &self.data, &self.loss);
+        self.best_cost = self.cost;
+        self.cost_history = vec![self.cost];
+        self.val_cost_history.clear();
+        self.validate();
+        if let Some(early_stopping) = &mut self.early_stopping {
+            early_stopping.started = false;
+        }
+    }
+
+    // The cost early stopping watches, the training cost standing in while
+    // there's no validation cost
+    fn monitored(&self) -> f32 {
+        match self.early_stopping.as_ref().map(|e| e.monitor) {
+            Some(Monitor::ValidationCost) => self.val_cost.unwrap_or(self.cost),
+            _ => self.cost,
+        }
+    }
+
+    fn validation_cost(&self) -> Option<f32> {
+        let validation = self.validation.as_ref()?;
+        if validation.is_empty() {
+            return None;
+        }
+
+        let scale = self.data.len() as f32 / validation.len() as f32;
+        Some(dataset::loss_cost(&self.nn, validation, &self.loss) * scale)
+    }
+
+    fn validate(&mut self) {
+        self.val_cost = self.validation_cost();
+        if let Some(val_cost) = self.val_cost {
+            self.val_cost_history.push(val_cost);
+        }
+    }
+
+    /// Trains until `epochs` epochs have completed in total or a callback
+    /// stops it.
+    pub fn fit(&mut self, epochs: usize, callbacks: &mut [&mut dyn Callback]) {
+        while self.epoch < epochs {
+            if self.step(callbacks) == Flow::Stop {
+                break;
+            }
+        }
+
+        self.restore_best();
+    }
+
+    /// Rolls the network back to the best weights early stopping has seen,
+    /// if it is set up to keep them.
+    pub fn restore_best(&mut self) {
+        let Some(early_stopping) = &self.early_stopping else {
+            return;
+        };
+        let Some(best) = &early_stopping.best_nn else {
+            return;
+        };
+
+        self.nn = best.clone();
+        self.cost = dataset::loss_cost(&self.nn, &self.data, &self.loss);
+        self.val_cost = self.validation_cost();
+    }
+}
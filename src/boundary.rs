@@ -0,0 +1,210 @@
+use std::cell::Cell;
+
+use super::{
+    color_lerp, draw::Renderinfo, draw_circle, draw_circle_lines, draw_rectangle_lines, draw_text,
+    draw_texture_ex, vec2, Color, DrawTextureParams, Mat, Texture2D, GRAY, NN, TEXT_COLOR, WHITE,
+};
+
+// Samples along each side of the heatmap
+const GRID_SIZE: usize = 64;
+// Share of the data's extent added around it on every side
+const MARGIN: f32 = 0.1;
+// How much of a class color the heatmap keeps, so the points stand out
+const SHADE: f32 = 0.6;
+
+// One color per output, the first two also being the ends of a single
+// output's range
+const CLASS_COLORS: [Color; 6] = [
+    Color {
+        r: 0.2,
+        g: 0.4,
+        b: 1.,
+        a: 1.,
+    },
+    Color {
+        r: 1.,
+        g: 0.5,
+        b: 0.1,
+        a: 1.,
+    },
+    Color {
+        r: 0.2,
+        g: 0.8,
+        b: 0.3,
+        a: 1.,
+    },
+    Color {
+        r: 0.9,
+        g: 0.2,
+        b: 0.3,
+        a: 1.,
+    },
+    Color {
+        r: 0.7,
+        g: 0.3,
+        b: 0.9,
+        a: 1.,
+    },
+    Color {
+        r: 0.9,
+        g: 0.9,
+        b: 0.2,
+        a: 1.,
+    },
+];
+
+/// The network's output over the plane of its two inputs, drawn as a heatmap
+/// under the training points so the decision boundary shows as it's learned.
+pub struct Boundary {
+    t_input: Mat,
+    t_output: Mat,
+    // input ranges covered by the heatmap, (min, max) for x then y
+    bounds: [(f32, f32); 2],
+    grid: Mat,
+    texture: Texture2D,
+    // the epoch and finished flag the heatmap was computed for
+    shown: Cell<Option<(i32, bool)>>,
+}
+
+impl Boundary {
+    /// Returns `None` unless the rows have exactly two inputs.
+    pub fn new(t_input: &Mat, t_output: &Mat) -> Option<Boundary> {
+        if t_input.cols != 2 || t_input.rows == 0 {
+            return None;
+        }
+
+        let bounds = [0, 1].map(|col| {
+            let values = t_input.data.iter().map(|row| row[col]);
+            let min = values.clone().fold(f32::INFINITY, f32::min);
+            let max = values.fold(f32::NEG_INFINITY, f32::max);
+            let margin = ((max - min) * MARGIN).max(0.5 * MARGIN);
+            (min - margin, max + margin)
+        });
+
+        // The top row of the heatmap is the largest y
+        let mut grid = Mat {
+            rows: GRID_SIZE * GRID_SIZE,
+            cols: 2,
+            data: Vec::with_capacity(GRID_SIZE * GRID_SIZE),
+        };
+        for row in 0..GRID_SIZE {
+            for col in 0..GRID_SIZE {
+                let tx = col as f32 / (GRID_SIZE - 1) as f32;
+                let ty = 1.0 - row as f32 / (GRID_SIZE - 1) as f32;
+                grid.data.push(vec![
+                    bounds[0].0 + tx * (bounds[0].1 - bounds[0].0),
+                    bounds[1].0 + ty * (bounds[1].1 - bounds[1].0),
+                ]);
+            }
+        }
+
+        let texture = Texture2D::from_rgba8(
+            GRID_SIZE as u16,
+            GRID_SIZE as u16,
+            &vec![0; GRID_SIZE * GRID_SIZE * 4],
+        );
+
+        Some(Boundary {
+            t_input: t_input.clone(),
+            t_output: t_output.clone(),
+            bounds,
+            grid,
+            texture,
+            shown: Cell::new(None),
+        })
+    }
+
+    /// Draws the heatmap and the training points in the largest square
+    /// fitting the box at `x`, `y`. The heatmap is only recomputed once the
+    /// network has changed, which happens after an epoch or when training
+    /// finishes and restores the best weights.
+    pub fn draw(&self, nn: &NN, info: &Renderinfo, x: f32, y: f32, width: f32, height: f32) {
+        let state = Some((info.epoch, info.finished));
+        if self.shown.get() != state {
+            self.shown.set(state);
+            self.update(nn);
+        }
+
+        let size = width.min(height - 20.0).max(0.0);
+        let (px, py) = (x, y + 20.0);
+        draw_texture_ex(
+            self.texture,
+            px,
+            py,
+            WHITE,
+            DrawTextureParams {
+                dest_size: Some(vec2(size, size)),
+                ..Default::default()
+            },
+        );
+        draw_rectangle_lines(px, py, size, size, 1.0, GRAY);
+        draw_text(
+            &format!(
+                "decision boundary | x {:.2}..{:.2}, y {:.2}..{:.2}",
+                self.bounds[0].0, self.bounds[0].1, self.bounds[1].0, self.bounds[1].1
+            ),
+            px,
+            y + 15.0,
+            20.0,
+            TEXT_COLOR,
+        );
+
+        let radius = (size * 0.012).max(2.0);
+        for (input, target) in self.t_input.data.iter().zip(&self.t_output.data) {
+            let tx = (input[0] - self.bounds[0].0) / (self.bounds[0].1 - self.bounds[0].0);
+            let ty = (input[1] - self.bounds[1].0) / (self.bounds[1].1 - self.bounds[1].0);
+            let (cx, cy) = (px + tx * size, py + (1.0 - ty) * size);
+            draw_circle(cx, cy, radius, output_color(target));
+            draw_circle_lines(cx, cy, radius, 1.0, WHITE);
+        }
+    }
+
+    fn update(&self, nn: &NN) {
+        let outputs = NN::predict(nn, &self.grid);
+        let bytes: Vec<u8> = outputs
+            .data
+            .iter()
+            .flat_map(|row| {
+                let color = output_color(row);
+                [color.r, color.g, color.b]
+                    .map(|channel| (channel * SHADE * 255.0) as u8)
+                    .into_iter()
+                    .chain([255])
+            })
+            .collect();
+        self.texture.update(&macroquad::texture::Image {
+            bytes,
+            width: GRID_SIZE as u16,
+            height: GRID_SIZE as u16,
+        });
+    }
+}
+
+// A new one is made on every reset, and textures aren't freed on their own
+impl Drop for Boundary {
+    fn drop(&mut self) {
+        self.texture.delete();
+    }
+}
+
+// Blends the class colors by how strongly each output fires. A single output
+// goes from the first color at 0 to the second at 1.
+fn output_color(outputs: &[f32]) -> Color {
+    if let [value] = outputs {
+        return color_lerp(CLASS_COLORS[0], CLASS_COLORS[1], value.clamp(0.0, 1.0));
+    }
+
+    let total: f32 = outputs.iter().map(|value| value.clamp(0.0, 1.0)).sum();
+    if total <= f32::EPSILON {
+        return GRAY;
+    }
+    let mut color = Color::new(0., 0., 0., 1.);
+    for (i, value) in outputs.iter().enumerate() {
+        let class = CLASS_COLORS[i % CLASS_COLORS.len()];
+        let weight = value.clamp(0.0, 1.0) / total;
+        color.r += class.r * weight;
+        color.g += class.g * weight;
+        color.b += class.b * weight;
+    }
+    color
+}
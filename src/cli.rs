@@ -0,0 +1,377 @@
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+use framework::{
+    augment::Augmentations,
+    config::Config,
+    csv::{self, Column, CsvOptions, Schema},
+    metrics::{Classification, Regression, Residual, Residuals},
+    model::Model,
+    preprocess::Pipeline,
+    trainer::{Callback, Flow, Trainer},
+    Mat, NN,
+};
+
+use super::{
+    picker::{Choice, Data, DatasetPicker},
+    training::{is_classification, new_trainer, print_report, Progress},
+};
+
+const USAGE: &str = "\
+Usage:
+  nn-rust [--config run.json] [data.csv | images.idx labels.idx | image...]   open the visualizer
+  nn-rust train [--config run.json] [--model model.json] [--log log.csv] [data.csv | images.idx labels.idx | image...]
+  nn-rust --headless ...   same as train, with the visualizer's arguments
+  nn-rust predict --model model.json --input rows.csv [--output predictions.csv]
+  nn-rust eval --model model.json --data data.csv [--residuals residuals.csv]
+  nn-rust inspect --model model.json
+
+A data file given to `train` replaces the config's data set. CSV files have a
+header line. IDX files (`.idx` or MNIST's `-ubyte` names) come as the images
+file followed by its labels file. `predict` reads only input columns, `eval` expects the targets in
+the last columns.";
+
+const COMMANDS: &[&str] = &[
+    "train", "predict", "eval", "inspect", "help", "--help", "-h",
+];
+
+pub fn is_command(arg: &str) -> bool {
+    COMMANDS.contains(&arg)
+}
+
+pub fn run(command: &str, args: &[String]) -> Result<(), String> {
+    let options = Options::parse(args)?;
+    match command {
+        "train" => train(&options),
+        "predict" => predict(&options),
+        "eval" => eval(&options),
+        "inspect" => inspect(&options),
+        _ => {
+            println!("{}", USAGE);
+            Ok(())
+        }
+    }
+}
+
+// `--name value` pairs and the paths between them
+struct Options {
+    named: HashMap<String, String>,
+    paths: Vec<String>,
+}
+
+impl Options {
+    fn parse(args: &[String]) -> Result<Options, String> {
+        let mut named = HashMap::new();
+        let mut paths = Vec::new();
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            let Some(name) = arg.strip_prefix("--") else {
+                paths.push(arg.clone());
+                continue;
+            };
+            let value = args
+                .next()
+                .ok_or_else(|| format!("--{} needs a value", name))?;
+            named.insert(name.to_owned(), value.clone());
+        }
+        Ok(Options { named, paths })
+    }
+
+    // Fails on any option not in `known`, and on paths unless they're allowed
+    fn check(&self, known: &[&str], paths: bool) -> Result<(), String> {
+        if let Some(name) = self
+            .named
+            .keys()
+            .find(|name| !known.contains(&name.as_str()))
+        {
+            return Err(format!("unknown option --{}\n\n{}", name, USAGE));
+        }
+        match self.paths.first() {
+            Some(path) if !paths => Err(format!("unexpected argument `{}`\n\n{}", path, USAGE)),
+            _ => Ok(()),
+        }
+    }
+
+    fn get(&self, name: &str) -> Option<&str> {
+        self.named.get(name).map(String::as_str)
+    }
+
+    fn require(&self, name: &str) -> Result<&str, String> {
+        self.get(name)
+            .ok_or_else(|| format!("missing --{}\n\n{}", name, USAGE))
+    }
+}
+
+/// Writes the costs of every epoch as CSV.
+struct LogCallback<W: Write> {
+    w: W,
+    error: Option<io::Error>,
+}
+
+impl<W: Write> Callback for LogCallback<W> {
+    fn on_epoch_end(&mut self, trainer: &Trainer) -> Flow {
+        let val_cost = trainer.val_cost.map_or_else(String::new, |c| c.to_string());
+        let result = writeln!(
+            self.w,
+            "{},{},{},{}",
+            trainer.epoch, trainer.cost, val_cost, trainer.learning_rate
+        );
+        match result {
+            Ok(()) => Flow::Continue,
+            Err(err) => {
+                self.error = Some(err);
+                Flow::Stop
+            }
+        }
+    }
+}
+
+fn train(options: &Options) -> Result<(), String> {
+    options.check(&["config", "model", "log"], true)?;
+    let config = match options.get("config") {
+        Some(path) => Config::load(path).map_err(|err| format!("{}: {}", path, err))?,
+        None => Config::default(),
+    };
+    let model_path = options.get("model").unwrap_or("model.json");
+    let log_path = options.get("log").unwrap_or("training-log.csv");
+
+    let choice = match config.dataset.as_deref() {
+        _ if !options.paths.is_empty() => Choice::from_paths(&options.paths)?,
+        None => DatasetPicker::builtin("xor").unwrap(),
+        Some(path) if Path::new(path).exists() => Choice::load(path)?,
+        Some(name) => DatasetPicker::builtin(name)
+            .ok_or_else(|| format!("`dataset`: no file or built-in data set named `{}`", name))?,
+    };
+    let Data::Table(t_input, t_output) = &choice.data else {
+        unreachable!("only the visualizer loads image choices");
+    };
+    if config.augmentation != Augmentations::default() {
+        if choice.image.is_none() {
+            return Err("`augmentation` needs a data set of images, like an IDX pair".to_owned());
+        }
+        if !config.input_pipeline.is_empty() {
+            return Err("`augmentation` can't be combined with an `input_pipeline`".to_owned());
+        }
+    }
+
+    let mut input = config.input_pipeline.clone();
+    let mut output = config.output_pipeline.clone();
+    let (mut trainer, t_input, t_output) = new_trainer(
+        &config,
+        &choice,
+        t_input.clone(),
+        t_output.clone(),
+        &mut input,
+        &mut output,
+    );
+    let arch: Vec<usize> = trainer.nn.activations.iter().map(|a| a.cols).collect();
+    println!(
+        "Training {:?} on {} ({} rows) for up to {} epochs",
+        arch, choice.name, t_input.rows, config.epochs
+    );
+    println!("Initial cost: {}", trainer.cost);
+
+    let file = File::create(log_path).map_err(|err| format!("{}: {}", log_path, err))?;
+    let mut log = LogCallback {
+        w: BufWriter::new(file),
+        error: None,
+    };
+    let header = writeln!(log.w, "epoch,cost,val_cost,learning_rate");
+    header.map_err(|err| format!("{}: {}", log_path, err))?;
+
+    let mut progress = Progress::new(config.epochs);
+    trainer.fit(config.epochs, &mut [&mut log, &mut progress]);
+    progress.finish(&trainer);
+    if let Some(err) = log.error.take().or_else(|| log.w.flush().err()) {
+        return Err(format!("{}: {}", log_path, err));
+    }
+
+    print_report(&trainer, &t_input, &t_output);
+
+    let model = Model {
+        input,
+        output,
+        columns: choice.schema,
+        ..Model::new(trainer.nn)
+    };
+    model
+        .save(model_path)
+        .map_err(|err| format!("{}: {}", model_path, err))?;
+    println!(
+        "Saved the model to {} and the log to {}",
+        model_path, log_path
+    );
+    Ok(())
+}
+
+fn predict(options: &Options) -> Result<(), String> {
+    options.check(&["model", "input", "output"], false)?;
+    let model = load_model(options.require("model")?)?;
+    let (input, _) = load_table(options.require("input")?, &model, false)?;
+
+    let predictions = model.predict(&input);
+    let mut out: Box<dyn Write> = match options.get("output") {
+        Some(path) => Box::new(BufWriter::new(
+            File::create(path).map_err(|err| format!("{}: {}", path, err))?,
+        )),
+        None => Box::new(io::stdout().lock()),
+    };
+    write_rows(&mut out, &predictions).map_err(|err| err.to_string())
+}
+
+fn eval(options: &Options) -> Result<(), String> {
+    options.check(&["model", "data", "residuals"], false)?;
+    let model = load_model(options.require("model")?)?;
+
+    let (input, target) = load_table(options.require("data")?, &model, true)?;
+
+    let nn_input = model.input.transform(&input);
+    let nn_target = model.output.transform(&target);
+    println!("Rows: {}", input.rows);
+    println!("Cost: {}", NN::cost(&model.nn, &nn_input, &nn_target));
+    if is_classification(&nn_target) {
+        println!(
+            "{}",
+            Classification::evaluate(&model.nn, &nn_input, &nn_target)
+        );
+    }
+
+    // Regression errors in the data's own units
+    let predictions = model.predict(&input);
+    let residuals = Residuals {
+        rows: (0..input.rows)
+            .map(|i| Residual {
+                input: input.data[i].clone(),
+                target: target.data[i].clone(),
+                prediction: predictions.data[i].clone(),
+            })
+            .collect(),
+    };
+    println!("{}", Regression::from_residuals(&residuals));
+
+    if let Some(path) = options.get("residuals") {
+        residuals
+            .save_csv(path)
+            .map_err(|err| format!("{}: {}", path, err))?;
+        println!("Saved the residuals to {}", path);
+    }
+    Ok(())
+}
+
+fn inspect(options: &Options) -> Result<(), String> {
+    options.check(&["model"], false)?;
+    let model = load_model(options.require("model")?)?;
+    let nn = &model.nn;
+
+    let arch: Vec<usize> = nn.activations.iter().map(|a| a.cols).collect();
+    let parameters: usize = (0..nn.count - 1)
+        .map(|i| nn.weights[i].rows * nn.weights[i].cols + nn.biases[i].cols)
+        .sum();
+    println!("Architecture: {:?} ({} parameters)", arch, parameters);
+
+    for (name, pipeline) in [("Input", &model.input), ("Output", &model.output)] {
+        let steps: Vec<String> = pipeline
+            .steps
+            .iter()
+            .map(|step| step.to_json()["type"].as_str().unwrap_or("?").to_owned())
+            .collect();
+        if steps.is_empty() {
+            println!("{} pipeline: none", name);
+        } else {
+            println!("{} pipeline: {}", name, steps.join(" -> "));
+        }
+    }
+
+    println!(
+        "{:>6} {:>8} {:>10} {:>10} {:>10} {:>10}",
+        "layer", "", "min", "max", "mean", "std"
+    );
+    for i in 0..nn.count - 1 {
+        for (name, mat) in [("weights", &nn.weights[i]), ("biases", &nn.biases[i])] {
+            let values: Vec<f32> = mat.data.iter().flatten().cloned().collect();
+            let mean = values.iter().sum::<f32>() / values.len() as f32;
+            let std = (values.iter().map(|v| (v - mean) * (v - mean)).sum::<f32>()
+                / values.len() as f32)
+                .sqrt();
+            let min = values.iter().cloned().fold(f32::INFINITY, f32::min);
+            let max = values.iter().cloned().fold(f32::NEG_INFINITY, f32::max);
+            println!(
+                "{:>6} {:>8} {:>10.4} {:>10.4} {:>10.4} {:>10.4}",
+                i + 1,
+                name,
+                min,
+                max,
+                mean,
+                std
+            );
+        }
+    }
+    Ok(())
+}
+
+fn load_model(path: &str) -> Result<Model, String> {
+    Model::load(path).map_err(|err| format!("{}: {}", path, err))
+}
+
+// How the model's CSV columns are encoded, plain numbers for models that
+// weren't trained on a CSV file
+fn schema(model: &Model) -> Schema {
+    model.columns.clone().unwrap_or_else(|| {
+        let nn = &model.nn;
+        Schema::numeric(
+            raw_width(&model.input, nn.activations[0].cols),
+            raw_width(&model.output, nn.activations[nn.count - 1].cols),
+        )
+    })
+}
+
+// How many columns a row takes before `pipeline` turns it into `cols`
+fn raw_width(pipeline: &Pipeline, cols: usize) -> usize {
+    pipeline
+        .input_cols(cols)
+        .expect("models are checked when loaded")
+}
+
+// Rows of a CSV file with a header encoded the way `model` expects, the last
+// columns being the targets when `targets` is set
+fn load_table(path: &str, model: &Model, targets: bool) -> Result<(Mat, Mat), String> {
+    let mut schema = schema(model);
+    if !targets {
+        schema.targets.clear();
+    }
+    let text = std::fs::read_to_string(path).map_err(|err| format!("{}: {}", path, err))?;
+    let options = CsvOptions::default();
+    let width = csv::field_count(&text, &options).map_err(|err| format!("{}: {}", path, err))?;
+    let inputs = schema.inputs.len();
+    if width != inputs + schema.targets.len() {
+        return Err(format!(
+            "{}: the model takes {} columns, found {}",
+            path,
+            inputs + schema.targets.len(),
+            width
+        ));
+    }
+
+    let options = CsvOptions {
+        inputs: (0..inputs).map(Column::Index).collect(),
+        targets: (inputs..width).map(Column::Index).collect(),
+        ..options
+    };
+    let data = csv::parse_with_schema(&text, &options, &schema)
+        .map_err(|err| format!("{}: {}", path, err))?;
+    Ok((data.t_input, data.t_output))
+}
+
+fn write_rows<W: Write>(w: &mut W, mat: &Mat) -> io::Result<()> {
+    let header: Vec<String> = (0..mat.cols).map(|i| format!("prediction_{}", i)).collect();
+    writeln!(w, "{}", header.join(","))?;
+    for row in &mat.data {
+        let fields: Vec<String> = row.iter().map(|val| val.to_string()).collect();
+        writeln!(w, "{}", fields.join(","))?;
+    }
+    w.flush()
+}
@@ -1,8 +1,12 @@
+use std::cell::Cell;
+
 use macroquad::window::screen_width;
 
 use super::{
-    color_lerp, draw_circle, draw_line, draw_rectangle, draw_text, f32, screen_height, sigmoidf,
-    Color, Mat, EPOCH_MAX, GRAY, LEARNING_RATE, LINE_COLOR, NN, TEXT_COLOR,
+    boundary::Boundary, color_lerp, draw_circle, draw_circle_lines, draw_line, draw_rectangle,
+    draw_text, f32, graph::CostGraph, histogram::Histograms, is_key_pressed,
+    is_mouse_button_pressed, measure_text, mouse_position, screen_height, sigmoidf,
+    upscale::Upscaler, Color, KeyCode, Mat, MouseButton, GRAY, LINE_COLOR, NN, TEXT_COLOR, WHITE,
 };
 
 const LOW_COLOR: Color = Color {
@@ -19,39 +23,228 @@ const HIGH_COLOR: Color = Color {
     a: 1.,
 };
 
+// Gradients this many orders of magnitude below the largest one look vanished
+const GRADIENT_DECADES: f32 = 6.;
+// Share of the window's height the network is drawn in, the row list taking
+// the space below so clicks on one never reach the other
+const NETWORK_HEIGHT: f32 = 0.7;
+
+const VANISHED_COLOR: Color = Color {
+    r: 0.15,
+    g: 0.15,
+    b: 0.3,
+    a: 1.,
+};
+
+const STEEP_COLOR: Color = Color {
+    r: 1.,
+    g: 0.9,
+    b: 0.2,
+    a: 1.,
+};
+
 #[derive(Clone, Debug)]
 pub struct Renderinfo {
     pub epoch: i32,
     pub cost: f32,
     pub t_input: Mat,
     pub t_output: Mat,
+    /// `(min, max)` of every input column, see [`column_ranges`].
+    pub input_ranges: Vec<(f32, f32)>,
     pub training_time: f32,
+    pub learning_rate: f32,
+    /// Epochs the run stops at.
+    pub epochs: usize,
     pub cost_history: Vec<f32>,
+    /// Epoch each cost in the history was measured at.
+    pub epoch_history: Vec<i32>,
+    pub val_cost: Option<f32>,
+    pub val_cost_history: Vec<f32>,
     pub paused: bool,
+    pub finished: bool,
+    /// Name of the data set being learned.
+    pub dataset: String,
+    /// Gradient of the last training step.
+    pub gradient: NN,
+}
+
+/// What the window shows besides the training state, kept across resets.
+#[derive(Default)]
+pub struct View {
+    /// Colors the neurons by what they output for `sample` instead of by
+    /// their bias.
+    pub activations: bool,
+    /// Colors the connections by how large their gradients are and shows
+    /// each layer's gradient norm.
+    pub gradients: bool,
+    /// Shows how the weights, biases and activations are spread over time
+    /// in place of the data set's own panel.
+    pub histograms: bool,
+    pub graph: CostGraph,
+    /// Training row fed through the network, shown in activation mode.
+    pub sample: usize,
+    /// Neuron whose details stay on screen, as layer and index.
+    pub pinned: Option<(usize, usize)>,
+    // the neuron under the mouse when the network was last drawn
+    hovered: Cell<Option<(usize, usize)>>,
+}
+
+impl View {
+    /// `a` toggles activation mode, `g` gradient mode and `h` the
+    /// histograms. Up and down step
+    /// through the `rows` training rows and clicking one in the list shows
+    /// it. Clicking a neuron pins it, clicking it again unpins it.
+    pub fn handle_input(&mut self, rows: usize) {
+        if is_key_pressed(KeyCode::A) {
+            self.activations = !self.activations;
+        }
+        if is_key_pressed(KeyCode::G) {
+            self.gradients = !self.gradients;
+        }
+        if is_key_pressed(KeyCode::H) {
+            self.histograms = !self.histograms;
+        }
+        self.graph.handle_input();
+        if is_mouse_button_pressed(MouseButton::Left) {
+            if let Some(row) = hovered_row(rows) {
+                self.activations = true;
+                self.sample = row;
+            }
+            // Input neurons have nothing to pin
+            if let Some(neuron) = self.hovered.get().filter(|(l, _)| *l > 0) {
+                self.pinned = if self.pinned == Some(neuron) {
+                    None
+                } else {
+                    Some(neuron)
+                };
+            }
+        }
+        // The list grows upwards from the first row
+        if self.activations && is_key_pressed(KeyCode::Up) {
+            self.sample += 1;
+        }
+        if self.activations && is_key_pressed(KeyCode::Down) {
+            self.sample = self.sample.saturating_sub(1);
+        }
+        self.sample = self.sample.min(rows.saturating_sub(1));
+    }
 }
 
-pub fn draw_frame(nn: &NN, info: &mut Renderinfo) {
-    let nn = nn.clone();
+pub fn draw_frame(
+    nn: NN,
+    info: &mut Renderinfo,
+    view: &View,
+    upscaler: Option<&Upscaler>,
+    boundary: Option<&Boundary>,
+    histograms: &mut Histograms,
+) {
     let (width, height) = (screen_width(), screen_height());
 
     // Skip epoch 0 because the value is already in the cost history (from creating the struct)
-    if info.epoch < EPOCH_MAX && !info.paused && info.epoch != 0 {
+    if !info.finished && !info.paused && info.epoch != 0 {
         let cost = NN::cost(&nn, &info.t_input, &info.t_output);
 
         info.cost = cost;
         info.cost_history.push(cost);
+        info.epoch_history.push(info.epoch);
+        if let Some(val_cost) = info.val_cost {
+            info.val_cost_history.push(val_cost);
+        }
+        histograms.record(&nn, info.epoch as usize);
+    }
+
+    // Hovering a row in the list shows it without selecting it
+    let sample = hovered_row(info.t_input.rows)
+        .or(Some(view.sample))
+        .filter(|row| *row < info.t_input.rows);
+    let mut shown = nn.clone();
+    if let Some(row) = sample {
+        shown.activations[0].data[0].copy_from_slice(&info.t_input.data[row]);
+        NN::forward(&mut shown);
     }
+    // The pinned neuron may be gone after switching data sets
+    let pinned = view
+        .pinned
+        .filter(|(l, i)| *l < nn.count && *i < nn.activations[*l].cols);
 
-    draw_nn(&nn, width, height * 0.8);
-    draw_graph(width, height, info);
-    draw_data(info, nn);
+    // A panel takes the right half of the network's space
+    let panel = (width * 0.5, 120., width * 0.5 - 10., height * 0.7 - 170.);
+    let nn_width = if view.histograms || upscaler.is_some() || boundary.is_some() {
+        width * 0.5
+    } else {
+        width
+    };
+    let (hovered, tooltip) = draw_nn(
+        &shown,
+        &info.gradient,
+        &info.input_ranges,
+        nn_width,
+        height * NETWORK_HEIGHT,
+        view,
+        pinned,
+    );
+    view.hovered.set(hovered);
+    if view.histograms {
+        histograms.draw(panel.0, panel.1, panel.2, panel.3);
+    } else if let Some(upscaler) = upscaler {
+        upscaler.draw(&nn, info, panel.0, panel.1, panel.2, panel.3);
+    } else if let Some(boundary) = boundary {
+        boundary.draw(&nn, info, panel.0, panel.1, panel.2, panel.3);
+    }
+    // The cost graph in the bottom right
+    view.graph
+        .draw(info, width * 0.7, height * 0.7, width * 0.3, height * 0.3);
+    draw_data(info, nn, sample.filter(|_| view.activations));
+    if let Some(neuron) = pinned {
+        draw_pinned(
+            &shown,
+            &info.gradient,
+            neuron,
+            width * 0.35,
+            height * 0.7,
+            height * 0.3 - 10.,
+        );
+    }
 
     draw_text("r - reset", width - 100., 20., 20., TEXT_COLOR);
     draw_text("p - pause", width - 100., 40., 20., TEXT_COLOR);
     draw_text("q - quit", width - 100., 60., 20., TEXT_COLOR);
+    let dataset = format!("d - {}", info.dataset);
+    let dataset_width = measure_text(&dataset, None, 20, 1.).width;
+    draw_text(&dataset, width - dataset_width - 10., 80., 20., TEXT_COLOR);
+    // The views that can be switched on, lit up when they are
+    let mut right = width - 10.;
+    for (label, on) in [
+        ("h - histograms", view.histograms),
+        ("g - gradients", view.gradients),
+        ("a - activations", view.activations),
+    ] {
+        right -= measure_text(label, None, 20, 1.).width;
+        draw_text(label, right, 100., 20., if on { TEXT_COLOR } else { GRAY });
+        right -= 15.;
+    }
+
+    // Last, so it's on top of everything
+    if !tooltip.is_empty() {
+        let (mouse_x, mouse_y) = mouse_position();
+        draw_tooltip(&tooltip, mouse_x, mouse_y);
+    }
 }
 
-fn draw_nn(nn: &NN, width: f32, height: f32) {
+/// Colors the neurons by their activations, `nn` having been run on the
+/// shown sample, or else by their biases, and the connections by their
+/// gradients or else by their weights, as `view` says. Returns the hovered
+/// neuron and the lines describing whatever is hovered, with its share of
+/// `gradient`.
+fn draw_nn(
+    nn: &NN,
+    gradient: &NN,
+    input_ranges: &[(f32, f32)],
+    width: f32,
+    height: f32,
+    view: &View,
+    pinned: Option<(usize, usize)>,
+) -> (Option<(usize, usize)>, Vec<String>) {
     let x = 0.;
     let y = 0.;
 
@@ -64,30 +257,62 @@ fn draw_nn(nn: &NN, width: f32, height: f32) {
     let nn_y = y + height / 2.0 - nn_height / 2.0;
     let arch_count = nn.count;
     let layer_hpad = nn_width / arch_count as f32;
+    let thick = height * 0.004;
+
+    let position = |l: usize, i: usize| {
+        let layer_vpad = nn_height / nn.activations[l].cols as f32;
+        (
+            nn_x + l as f32 * layer_hpad + layer_hpad / 2.0,
+            nn_y + i as f32 * layer_vpad + layer_vpad / 2.0,
+        )
+    };
+    let (mouse_x, mouse_y) = mouse_position();
+    let hovered = (0..arch_count)
+        .flat_map(|l| (0..nn.activations[l].cols).map(move |i| (l, i)))
+        .find(|(l, i)| {
+            let (cx, cy) = position(*l, *i);
+            (mouse_x - cx).powi(2) + (mouse_y - cy).powi(2) <= neuron_radius.powi(2)
+        });
+    let mut hovered_weight = None;
+    let steepest = (0..arch_count - 1)
+        .flat_map(|l| gradient.weights[l].data.iter().flatten())
+        .fold(0., |max: f32, g| max.max(g.abs()));
 
     for l in 0..arch_count {
-        let layer_vpad1 = nn_height / nn.activations[l].cols as f32;
         for i in 0..nn.activations[l].cols {
-            let cx1 = nn_x + l as f32 * layer_hpad + layer_hpad / 2.0;
-            let cy1 = nn_y + i as f32 * layer_vpad1 + layer_vpad1 / 2.0;
+            let (cx1, cy1) = position(l, i);
             if l + 1 < arch_count {
-                let layer_vpad2 = nn_height / nn.activations[l + 1].cols as f32;
                 for j in 0..nn.activations[l + 1].cols {
-                    let cx2 = nn_x + (l + 1) as f32 * layer_hpad + layer_hpad / 2.0;
-                    let cy2 = nn_y + j as f32 * layer_vpad2 + layer_vpad2 / 2.0;
-                    let value = sigmoidf(nn.weights[l].data[i][j]);
-                    let thick = height * 0.004;
-                    draw_line(
-                        cx1,
-                        cy1,
-                        cx2,
-                        cy2,
-                        thick,
-                        color_lerp(LOW_COLOR, HIGH_COLOR, value),
-                    );
+                    let (cx2, cy2) = position(l + 1, j);
+                    let color = if view.gradients {
+                        let value = gradient_scale(gradient.weights[l].data[i][j], steepest);
+                        color_lerp(VANISHED_COLOR, STEEP_COLOR, value)
+                    } else {
+                        let value = sigmoidf(nn.weights[l].data[i][j]);
+                        color_lerp(LOW_COLOR, HIGH_COLOR, value)
+                    };
+                    draw_line(cx1, cy1, cx2, cy2, thick, color);
+                    let near = segment_distance((mouse_x, mouse_y), (cx1, cy1), (cx2, cy2))
+                        <= thick.max(3.);
+                    if hovered.is_none() && hovered_weight.is_none() && near {
+                        hovered_weight = Some((l, i, j));
+                    }
                 }
             }
-            if l > 0 {
+            if view.activations {
+                let mut value = nn.activations[l].data[0][i];
+                // Inputs can be in any units, the data's own range stretches
+                // over the colors
+                if let (0, Some((min, max))) = (l, input_ranges.get(i)) {
+                    value = (value - min) / (max - min).max(f32::EPSILON);
+                }
+                draw_circle(
+                    cx1,
+                    cy1,
+                    neuron_radius,
+                    color_lerp(LOW_COLOR, HIGH_COLOR, value.clamp(0.0, 1.0)),
+                );
+            } else if l > 0 {
                 let value = sigmoidf(nn.biases[l - 1].data[0][i]);
                 draw_circle(
                     cx1,
@@ -98,71 +323,193 @@ fn draw_nn(nn: &NN, width: f32, height: f32) {
             } else {
                 draw_circle(cx1, cy1, neuron_radius, GRAY);
             }
+            if pinned == Some((l, i)) {
+                draw_circle_lines(cx1, cy1, neuron_radius + 2., 2.0, LINE_COLOR);
+            }
+        }
+    }
+
+    if view.gradients {
+        draw_gradient_norms(gradient, nn_y + nn_height, layer_border_vpad, |l| {
+            (position(l, 0).0 + position(l + 1, 0).0) / 2.
+        });
+    }
+
+    let mut tooltip = Vec::new();
+    if let Some((l, i, j)) = hovered_weight {
+        let ((cx1, cy1), (cx2, cy2)) = (position(l, i), position(l + 1, j));
+        draw_line(cx1, cy1, cx2, cy2, thick + 2., WHITE);
+        tooltip = vec![
+            format!("weight {}:{} -> {}:{}", l, i, l + 1, j),
+            format!("value {:.4}", nn.weights[l].data[i][j]),
+            format!("gradient {:.3e}", gradient.weights[l].data[i][j]),
+        ];
+    }
+    if let Some((l, i)) = hovered {
+        let (cx, cy) = position(l, i);
+        draw_circle_lines(cx, cy, neuron_radius, 2.0, WHITE);
+        tooltip = vec![
+            format!("layer {} neuron {}", l, i),
+            format!("activation {:.4}", nn.activations[l].data[0][i]),
+        ];
+        if l > 0 {
+            let (min, max, mean) = stats(nn.weights[l - 1].data.iter().map(|row| row[i]));
+            tooltip.push(format!("bias {:.4}", nn.biases[l - 1].data[0][i]));
+            tooltip.push(format!(
+                "weights in {:.3}..{:.3}, mean {:.3}",
+                min, max, mean
+            ));
+            tooltip.push("click to pin".to_owned());
         }
     }
+    (hovered, tooltip)
 }
 
-fn draw_graph(width: f32, height: f32, info: &Renderinfo) {
-    let x = 0.;
-    let y = 0.;
+// Where a gradient falls between vanished at 0 and the steepest one at 1, on
+// a log scale
+fn gradient_scale(gradient: f32, steepest: f32) -> f32 {
+    if steepest <= 0. || gradient == 0. {
+        return 0.;
+    }
+    ((gradient.abs() / steepest).log10() / GRADIENT_DECADES + 1.).clamp(0., 1.)
+}
+
+/// A bar per layer of weights, centered on `center(layer)` and growing up
+/// from `bottom`, as tall as the layer's gradient norm on the same log scale
+/// as the connections. The norm is written under it.
+fn draw_gradient_norms(gradient: &NN, bottom: f32, height: f32, center: impl Fn(usize) -> f32) {
+    let norms: Vec<f32> = (0..gradient.count - 1)
+        .map(|l| {
+            let weights = gradient.weights[l].data.iter().flatten();
+            let biases = gradient.biases[l].data.iter().flatten();
+            weights.chain(biases).map(|g| g * g).sum::<f32>().sqrt()
+        })
+        .collect();
+    let largest = norms.iter().cloned().fold(0., f32::max);
+
+    for (l, norm) in norms.iter().enumerate() {
+        let bar = gradient_scale(*norm, largest).max(0.02) * height * 0.6;
+        let x = center(l);
+        draw_rectangle(
+            x - 6.,
+            bottom - bar,
+            12.,
+            bar,
+            color_lerp(VANISHED_COLOR, STEEP_COLOR, gradient_scale(*norm, largest)),
+        );
+        let label = format!("{:.1e}", norm);
+        let label_width = measure_text(&label, None, 16, 1.).width;
+        draw_text(&label, x - label_width / 2., bottom + 14., 16., TEXT_COLOR);
+    }
+}
 
-    // Draw a cost history graph in the bottom right
-    let graph_width = width * 0.3;
-    let graph_height = height * 0.3;
-    let graph_x = x + width - graph_width;
-    let graph_y = y + height - graph_height;
+/// Every value feeding into and computed by a pinned neuron, in the box at
+/// `x`, `y`.
+fn draw_pinned(nn: &NN, gradient: &NN, (l, i): (usize, usize), x: f32, y: f32, height: f32) {
+    let weights = &nn.weights[l - 1];
+    let mut lines = vec![
+        format!("layer {} neuron {} (click to unpin)", l, i),
+        format!("activation {:.4}", nn.activations[l].data[0][i]),
+        format!(
+            "bias {:.4}, gradient {:.3e}",
+            nn.biases[l - 1].data[0][i],
+            gradient.biases[l - 1].data[0][i]
+        ),
+    ];
+    for k in 0..weights.rows {
+        lines.push(format!(
+            "from {}: {:+.4}, gradient {:.3e}",
+            k,
+            weights.data[k][i],
+            gradient.weights[l - 1].data[k][i]
+        ));
+    }
+
+    // Keep to the box, saying how much didn't fit
+    let fits = ((height / 20.) as usize).max(2);
+    if lines.len() > fits {
+        let hidden = lines.len() - fits + 1;
+        lines.truncate(fits - 1);
+        lines.push(format!("... {} more", hidden));
+    }
+    draw_tooltip(&lines, x, y);
+}
 
+// Lines of text on a dark box with its top left corner near `x`, `y`
+fn draw_tooltip(lines: &[String], x: f32, y: f32) {
+    let width = lines
+        .iter()
+        .map(|line| measure_text(line, None, 20, 1.).width)
+        .fold(0., f32::max);
+    // Flip to the left of the cursor at the right edge
+    let x = if x + 12. + width + 8. > screen_width() {
+        x - width - 20.
+    } else {
+        x + 12.
+    };
     draw_rectangle(
-        graph_x,
-        graph_y,
-        graph_width,
-        graph_height,
-        Color {
-            r: 0.2,
-            g: 0.2,
-            b: 0.2,
-            a: 0.5,
-        },
-    );
-    draw_text(
-        format!("Cost: {}", info.cost).as_str(),
-        graph_x,
-        graph_y - 5.,
-        20.,
+        x,
+        y,
+        width + 8.,
+        lines.len() as f32 * 20. + 6.,
         Color {
-            r: 1.,
-            g: 1.,
-            b: 1.,
-            a: 1.,
+            r: 0.1,
+            g: 0.1,
+            b: 0.1,
+            a: 0.9,
         },
     );
+    for (n, line) in lines.iter().enumerate() {
+        draw_text(line, x + 4., y + 18. + n as f32 * 20., 20., TEXT_COLOR);
+    }
+}
 
-    let mut max_cost = 0.;
-    // Check for max cost
-    for i in 0..info.cost_history.len() {
-        if info.cost_history[i] > max_cost {
-            max_cost = info.cost_history[i];
-        }
+fn segment_distance(p: (f32, f32), a: (f32, f32), b: (f32, f32)) -> f32 {
+    let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+    let length = dx * dx + dy * dy;
+    let t = if length > 0. {
+        (((p.0 - a.0) * dx + (p.1 - a.1) * dy) / length).clamp(0., 1.)
+    } else {
+        0.
+    };
+    ((p.0 - a.0 - t * dx).powi(2) + (p.1 - a.1 - t * dy).powi(2)).sqrt()
+}
+
+// Smallest, largest and mean value
+fn stats(values: impl Iterator<Item = f32>) -> (f32, f32, f32) {
+    let (mut min, mut max, mut sum, mut count) = (f32::INFINITY, f32::NEG_INFINITY, 0., 0);
+    for value in values {
+        min = min.min(value);
+        max = max.max(value);
+        sum += value;
+        count += 1;
     }
-    let mut last_x = graph_x;
-    let mut last_y;
-    last_y = graph_y + graph_height - info.cost_history[0] / max_cost * graph_height;
+    (min, max, sum / count.max(1) as f32)
+}
 
-    for i in 0..info.cost_history.len() {
-        let x = graph_x + i as f32 * graph_width / info.cost_history.len() as f32;
-        let y = graph_y + graph_height - info.cost_history[i] / max_cost * graph_height;
-        draw_line(last_x, last_y, x, y, 1., LINE_COLOR);
-        last_x = x;
-        last_y = y;
+// Baseline of a row in the list at the bottom left, `None` once the list
+// would run over the network. A row takes from 15 above its baseline to 5
+// below.
+fn row_y(row: usize) -> Option<f32> {
+    let y = screen_height() - 10. - row as f32 * 20.;
+    (y - 15. >= screen_height() * NETWORK_HEIGHT).then_some(y)
+}
+
+fn hovered_row(rows: usize) -> Option<usize> {
+    let (mouse_x, mouse_y) = mouse_position();
+    if mouse_x > screen_width() * 0.5 {
+        return None;
     }
+    (0..rows).find(|row| row_y(*row).is_some_and(|y| mouse_y > y - 15. && mouse_y <= y + 5.))
 }
 
-fn draw_data(info: &Renderinfo, mut nn: NN) {
+/// `sample` is the row shown in the network, marked in the list.
+fn draw_data(info: &Renderinfo, mut nn: NN, sample: Option<usize>) {
     // Top right parameters
     draw_text(
         format!(
             "Epoch: {}/{} | Learning Rate: {:.4}",
-            info.epoch, EPOCH_MAX, LEARNING_RATE
+            info.epoch, info.epochs, info.learning_rate
         )
         .as_str(),
         0.,
@@ -172,7 +519,12 @@ fn draw_data(info: &Renderinfo, mut nn: NN) {
     );
 
     draw_text(
-        format!("Training time: {:.2}s", info.training_time).as_str(),
+        format!(
+            "Training time: {:.2}s{}",
+            info.training_time,
+            if info.finished { " | Finished" } else { "" }
+        )
+        .as_str(),
         0.,
         30.,
         20.,
@@ -181,6 +533,18 @@ fn draw_data(info: &Renderinfo, mut nn: NN) {
 
     // Write the testing results at the bottom left
     for i in 0..info.t_input.rows {
+        // Keep big data sets from running over the network
+        let Some(y) = row_y(i) else {
+            draw_text(
+                format!("... {} more", info.t_input.rows - i).as_str(),
+                0.,
+                screen_height() - 10. - i as f32 * 20.,
+                20.,
+                TEXT_COLOR,
+            );
+            break;
+        };
+
         for j in 0..nn.activations[0].data[0].len() {
             nn.activations[0].data[0][j] = info.t_input.data[i][j];
         }
@@ -189,15 +553,30 @@ fn draw_data(info: &Renderinfo, mut nn: NN) {
         draw_text(
             format!(
                 // Input | Output
-                "{:?} -> {:?}",
+                "{}{:?} -> {:?}",
+                if sample == Some(i) { "> " } else { "" },
                 info.t_input.data[i],
                 nn.activations[nn.count - 1].data[0] // -1 because the last activation is the output
             )
             .as_str(),
             0.,
-            screen_height() - 10. - i as f32 * 20.,
+            y,
             20.,
             TEXT_COLOR,
         );
     }
 }
+
+/// `(min, max)` of every column of `mat`, computed once per data set since
+/// it goes over every row.
+pub fn column_ranges(mat: &Mat) -> Vec<(f32, f32)> {
+    (0..mat.cols)
+        .map(|col| {
+            mat.data
+                .iter()
+                .fold((f32::INFINITY, f32::NEG_INFINITY), |(lo, hi), row| {
+                    (lo.min(row[col]), hi.max(row[col]))
+                })
+        })
+        .collect()
+}
@@ -0,0 +1,340 @@
+use std::cell::{Cell, Ref, RefCell};
+
+use super::{
+    draw::Renderinfo, draw_line, draw_rectangle, draw_text, is_key_pressed, is_mouse_button_down,
+    is_mouse_button_pressed, measure_text, mouse_position, mouse_wheel, vec2, Color, KeyCode,
+    MouseButton, Rect, LINE_COLOR, TEXT_COLOR, VAL_LINE_COLOR,
+};
+
+// Smoothing factors `e` cycles through, 0 draws the raw costs
+const SMOOTHING: [f32; 3] = [0., 0.9, 0.99];
+// Smallest share of the history that can be zoomed in on
+const MIN_SPAN: f32 = 0.001;
+// How much one notch of the mouse wheel zooms
+const ZOOM_STEP: f32 = 0.8;
+// Costs are clamped to this on the log scale, which can't show 0
+const LOG_FLOOR: f32 = 1e-12;
+
+const GRID_COLOR: Color = Color {
+    r: 0.5,
+    g: 0.5,
+    b: 0.5,
+    a: 0.3,
+};
+
+/// The cost history of the training and validation sets. Scrolling over it
+/// zooms in on the epochs under the mouse, dragging pans and a right click
+/// shows everything again.
+pub struct CostGraph {
+    pub log_scale: bool,
+    /// Weight of the previous average in the exponential moving average, 0
+    /// for none.
+    pub smoothing: f32,
+    // visible part of the history, as shares of it so it keeps up with new
+    // costs
+    start: f32,
+    end: f32,
+    // where the plot was last drawn, for mouse input
+    plot: Cell<Rect>,
+    // mouse x while dragging
+    drag: Option<f32>,
+    // moving averages of the training and validation costs, extended as the
+    // histories grow
+    averages: RefCell<[Vec<f32>; 2]>,
+}
+
+impl Default for CostGraph {
+    fn default() -> CostGraph {
+        CostGraph {
+            log_scale: true,
+            smoothing: 0.,
+            start: 0.,
+            end: 1.,
+            plot: Cell::new(Rect::new(0., 0., 0., 0.)),
+            drag: None,
+            averages: RefCell::default(),
+        }
+    }
+}
+
+impl CostGraph {
+    /// `l` toggles the log scale and `e` steps through the smoothing factors,
+    /// the mouse zooms and pans.
+    pub fn handle_input(&mut self) {
+        if is_key_pressed(KeyCode::L) {
+            self.log_scale = !self.log_scale;
+        }
+        if is_key_pressed(KeyCode::E) {
+            let next = SMOOTHING
+                .iter()
+                .position(|s| *s == self.smoothing)
+                .map_or(0, |i| (i + 1) % SMOOTHING.len());
+            self.smoothing = SMOOTHING[next];
+            *self.averages.borrow_mut() = Default::default();
+        }
+
+        let plot = self.plot.get();
+        let (mouse_x, mouse_y) = mouse_position();
+        let inside = plot.w > 0. && plot.contains(vec2(mouse_x, mouse_y));
+        let span = self.end - self.start;
+
+        let (_, wheel) = mouse_wheel();
+        if inside && wheel != 0. {
+            let anchor = self.start + (mouse_x - plot.x) / plot.w * span;
+            let factor = if wheel > 0. {
+                ZOOM_STEP
+            } else {
+                1. / ZOOM_STEP
+            };
+            let zoomed = (span * factor).clamp(MIN_SPAN, 1.);
+            self.start = anchor - (anchor - self.start) * zoomed / span;
+            self.end = self.start + zoomed;
+        }
+
+        if inside && is_mouse_button_pressed(MouseButton::Left) {
+            self.drag = Some(mouse_x);
+        }
+        match self.drag {
+            Some(last) if is_mouse_button_down(MouseButton::Left) => {
+                let moved = (mouse_x - last) / plot.w * span;
+                self.start -= moved;
+                self.end -= moved;
+                self.drag = Some(mouse_x);
+            }
+            _ => self.drag = None,
+        }
+
+        if inside && is_mouse_button_pressed(MouseButton::Right) {
+            self.start = 0.;
+            self.end = 1.;
+        }
+
+        // Slide back into the history rather than shrinking
+        let span = self.end - self.start;
+        if self.start < 0. {
+            self.start = 0.;
+            self.end = span;
+        }
+        if self.end > 1. {
+            self.end = 1.;
+            self.start = 1. - span;
+        }
+    }
+
+    /// Draws the graph in the box at `x`, `y`, its labels above it.
+    pub fn draw(&self, info: &Renderinfo, x: f32, y: f32, width: f32, height: f32) {
+        draw_rectangle(
+            x,
+            y,
+            width,
+            height,
+            Color {
+                r: 0.2,
+                g: 0.2,
+                b: 0.2,
+                a: 0.5,
+            },
+        );
+        let label = match info.val_cost {
+            Some(val_cost) => format!("Cost: {} | Validation: {}", info.cost, val_cost),
+            None => format!("Cost: {}", info.cost),
+        };
+        draw_text(&label, x, y - 5., 20., TEXT_COLOR);
+        let keys = format!(
+            "l - {} | e - smoothing {} | scroll, drag",
+            if self.log_scale { "log" } else { "linear" },
+            self.smoothing
+        );
+        draw_text(&keys, x, y - 25., 20., TEXT_COLOR);
+
+        // Room for the tick labels on the left and at the bottom
+        let plot = Rect::new(x + 48., y + 6., width - 54., height - 24.);
+        self.plot.set(plot);
+        let count = info.cost_history.len();
+        if count == 0 || plot.w <= 0. || plot.h <= 0. {
+            return;
+        }
+
+        let last = (count - 1) as f32;
+        let first = (self.start * last).floor() as usize;
+        let end = ((self.end * last).ceil() as usize)
+            .max(first + 1)
+            .min(count);
+
+        // The costs are averaged before the scale is applied, averaging their
+        // logarithms would lean towards the low ones. Only the visible part
+        // is scaled.
+        let averages = self.averages(info);
+        let visible = |values: &[f32]| -> Vec<f32> {
+            values[first.min(values.len())..end.min(values.len())]
+                .iter()
+                .map(|value| self.scale(*value))
+                .collect()
+        };
+        let series: Vec<(&str, Color, Vec<f32>, Vec<f32>)> = [
+            ("train", LINE_COLOR, &info.cost_history, &averages[0]),
+            (
+                "validation",
+                VAL_LINE_COLOR,
+                &info.val_cost_history,
+                &averages[1],
+            ),
+        ]
+        .into_iter()
+        .filter(|(_, _, history, _)| !history.is_empty())
+        .map(|(name, color, history, average)| (name, color, visible(history), visible(average)))
+        .collect();
+
+        // The visible costs decide the range, their averages stay within it
+        let (mut low, mut high) = series
+            .iter()
+            .flat_map(|(_, _, values, _)| values)
+            .fold((f32::INFINITY, f32::NEG_INFINITY), |(lo, hi), v| {
+                (lo.min(*v), hi.max(*v))
+            });
+        if !low.is_finite() {
+            return;
+        }
+        if self.log_scale {
+            low = low.floor();
+            high = high.ceil().max(low + 1.);
+        } else {
+            low = 0.;
+            high = high.max(f32::EPSILON);
+        }
+        let to_y = |value: f32| {
+            let t = ((value - low) / (high - low)).clamp(0., 1.);
+            plot.y + plot.h - t * plot.h
+        };
+        let to_x =
+            |i: usize| plot.x + (i - first) as f32 / (end - 1 - first).max(1) as f32 * plot.w;
+
+        for (value, label) in self.y_ticks(low, high) {
+            let ty = to_y(value);
+            draw_line(plot.x, ty, plot.x + plot.w, ty, 1., GRID_COLOR);
+            let label_width = measure_text(&label, None, 16, 1.).width;
+            draw_text(&label, plot.x - label_width - 4., ty + 5., 16., TEXT_COLOR);
+        }
+        for tick in 0..4 {
+            let i = first + (end - 1 - first) * tick / 3;
+            let tx = to_x(i);
+            draw_line(tx, plot.y, tx, plot.y + plot.h, 1., GRID_COLOR);
+            let epoch = info.epoch_history.get(i).copied().unwrap_or(info.epoch);
+            let label = epoch.to_string();
+            let label_width = measure_text(&label, None, 16, 1.).width;
+            let lx = (tx - label_width / 2.)
+                .min(plot.x + plot.w - label_width)
+                .max(plot.x);
+            draw_text(&label, lx, plot.y + plot.h + 15., 16., TEXT_COLOR);
+        }
+
+        // A point per pixel at most
+        let step = ((end - first) as f32 / plot.w).ceil().max(1.) as usize;
+        for (_, color, values, average) in &series {
+            if self.smoothing > 0. {
+                let faint = Color { a: 0.3, ..*color };
+                draw_series(values, first, step, faint, &to_x, &to_y);
+                draw_series(average, first, step, *color, &to_x, &to_y);
+            } else {
+                draw_series(values, first, step, *color, &to_x, &to_y);
+            }
+        }
+
+        // Legend in the top right corner
+        let mut ly = plot.y + 14.;
+        for (name, color, _, _) in &series {
+            let name_width = measure_text(name, None, 16, 1.).width;
+            let lx = plot.x + plot.w - name_width - 4.;
+            draw_line(lx - 18., ly - 5., lx - 4., ly - 5., 2., *color);
+            draw_text(name, lx, ly, 16., TEXT_COLOR);
+            ly += 16.;
+        }
+    }
+
+    // A cost on the graph's scale
+    fn scale(&self, cost: f32) -> f32 {
+        if self.log_scale {
+            cost.max(LOG_FLOOR).log10()
+        } else {
+            cost
+        }
+    }
+
+    // Exponential moving averages of the training and validation costs,
+    // starting from their first values. Only new costs are averaged, unless
+    // the history was reset.
+    fn averages(&self, info: &Renderinfo) -> Ref<'_, [Vec<f32>; 2]> {
+        if self.smoothing > 0. {
+            let mut averages = self.averages.borrow_mut();
+            let histories = [&info.cost_history, &info.val_cost_history];
+            for (average, history) in averages.iter_mut().zip(histories) {
+                if average.len() > history.len() || average.first() != history.first() {
+                    average.clear();
+                }
+                let mut last = average.last().or(history.first()).copied();
+                for value in &history[average.len()..] {
+                    let next = last.map_or(*value, |last| {
+                        self.smoothing * last + (1. - self.smoothing) * value
+                    });
+                    average.push(next);
+                    last = Some(next);
+                }
+            }
+        }
+        self.averages.borrow()
+    }
+
+    // Gridlines and their labels between `low` and `high` on the graph's
+    // scale: every power of ten on the log scale, round numbers otherwise
+    fn y_ticks(&self, low: f32, high: f32) -> Vec<(f32, String)> {
+        if self.log_scale {
+            let every = ((high - low) / 5.).ceil().max(1.) as i32;
+            (low as i32..=high as i32)
+                .step_by(every as usize)
+                .map(|power| (power as f32, format!("1e{}", power)))
+                .collect()
+        } else {
+            let rough = high / 4.;
+            let magnitude = 10f32.powf(rough.log10().floor());
+            let step = [1., 2., 5., 10.]
+                .into_iter()
+                .map(|m| m * magnitude)
+                .find(|step| *step >= rough)
+                .unwrap_or(rough);
+            (0..=4)
+                .map(|i| i as f32 * step)
+                .take_while(|value| *value <= high)
+                .map(|value| (value, format_tick(value)))
+                .collect()
+        }
+    }
+}
+
+fn format_tick(value: f32) -> String {
+    if value != 0. && !(0.01..1000.).contains(&value) {
+        format!("{:.0e}", value)
+    } else {
+        format!("{}", (value * 1000.).round() / 1000.)
+    }
+}
+
+// `values` being the visible part of a series, starting at index `first`
+fn draw_series(
+    values: &[f32],
+    first: usize,
+    step: usize,
+    color: Color,
+    to_x: &impl Fn(usize) -> f32,
+    to_y: &impl Fn(f32) -> f32,
+) {
+    if values.is_empty() {
+        return;
+    }
+
+    let mut last = (to_x(first), to_y(values[0]));
+    for i in (0..values.len()).step_by(step).chain([values.len() - 1]) {
+        let point = (to_x(first + i), to_y(values[i]));
+        draw_line(last.0, last.1, point.0, point.1, 1., color);
+        last = point;
+    }
+}
@@ -0,0 +1,240 @@
+use super::{
+    color_lerp, draw_rectangle_lines, draw_text, draw_texture_ex, measure_text, vec2, Color,
+    DrawTextureParams, FilterMode, Mat, Texture2D, GRAY, NN, TEXT_COLOR, WHITE,
+};
+
+// Snapshots kept, older ones are thinned out to make room
+const HISTORY: usize = 128;
+// Value buckets in each histogram
+const BINS: usize = 32;
+// Training rows fed through the network for the activation histograms
+const ACTIVATION_ROWS: usize = 64;
+
+const EMPTY_COLOR: Color = Color {
+    r: 0.05,
+    g: 0.05,
+    b: 0.15,
+    a: 1.,
+};
+
+const FULL_COLOR: Color = Color {
+    r: 1.,
+    g: 0.8,
+    b: 0.2,
+    a: 1.,
+};
+
+/// Weights, biases and activations of every layer over the whole run, drawn
+/// as heat strips: time goes right, values go up and brighter buckets hold
+/// more of them.
+pub struct Histograms {
+    // per snapshot, per strip, the values in it
+    snapshots: Vec<Vec<Vec<f32>>>,
+    // epoch of every snapshot
+    epochs: Vec<usize>,
+    // epochs between snapshots, doubled every time the history is thinned
+    every: usize,
+    rows: Mat,
+    texture: Texture2D,
+    strips: usize,
+    // (min, max) of every strip, as of the last snapshot
+    ranges: Vec<(f32, f32)>,
+}
+
+impl Histograms {
+    /// `t_input` gives the rows the activations are measured on.
+    pub fn new(nn: &NN, t_input: &Mat) -> Histograms {
+        let step = (t_input.rows / ACTIVATION_ROWS).max(1);
+        let data: Vec<Vec<f32>> = t_input.data.iter().step_by(step).cloned().collect();
+        let rows = Mat {
+            rows: data.len(),
+            cols: t_input.cols,
+            data,
+        };
+
+        // Weights, biases and activations for every layer past the input
+        let strips = (nn.count - 1) * 3;
+        let texture = Texture2D::from_rgba8(
+            HISTORY as u16,
+            (strips * BINS) as u16,
+            &vec![0; HISTORY * strips * BINS * 4],
+        );
+        texture.set_filter(FilterMode::Nearest);
+
+        Histograms {
+            snapshots: Vec::new(),
+            epochs: Vec::new(),
+            every: 1,
+            rows,
+            texture,
+            strips,
+            ranges: Vec::new(),
+        }
+    }
+
+    /// Takes a snapshot of `nn` when one is due at `epoch`, called every
+    /// frame while training.
+    pub fn record(&mut self, nn: &NN, epoch: usize) {
+        if self
+            .epochs
+            .last()
+            .is_some_and(|last| epoch < last + self.every)
+        {
+            return;
+        }
+
+        let mut activations = vec![Vec::new(); nn.count - 1];
+        let mut shown = nn.clone();
+        for row in 0..self.rows.rows {
+            Mat::copy(&mut shown.activations[0], &Mat::row(&self.rows, row));
+            NN::forward(&mut shown);
+            for l in 1..nn.count {
+                activations[l - 1].extend_from_slice(&shown.activations[l].data[0]);
+            }
+        }
+
+        let mut snapshot = Vec::with_capacity(self.strips);
+        for (l, activations) in activations.into_iter().enumerate() {
+            snapshot.push(nn.weights[l].data.concat());
+            snapshot.push(nn.biases[l].data.concat());
+            snapshot.push(activations);
+        }
+        self.snapshots.push(snapshot);
+        self.epochs.push(epoch);
+
+        if self.snapshots.len() >= HISTORY {
+            let mut i = 0;
+            self.snapshots.retain(|_| {
+                i += 1;
+                i % 2 == 1
+            });
+            let mut i = 0;
+            self.epochs.retain(|_| {
+                i += 1;
+                i % 2 == 1
+            });
+            self.every *= 2;
+        }
+
+        self.update_texture();
+    }
+
+    // Bins every snapshot into the texture, only needed when one was added
+    // since the ranges can change with it
+    fn update_texture(&mut self) {
+        // Each strip spans the values it ever held, so they stay comparable
+        // over time
+        self.ranges = (0..self.strips)
+            .map(|strip| {
+                let values = self.snapshots.iter().flat_map(|s| s[strip].iter());
+                let (min, max) = values.fold((f32::INFINITY, f32::NEG_INFINITY), |(lo, hi), v| {
+                    (lo.min(*v), hi.max(*v))
+                });
+                if max <= min + f32::EPSILON {
+                    (min - 0.5, max + 0.5)
+                } else {
+                    (min, max)
+                }
+            })
+            .collect();
+
+        let mut bytes = vec![0; HISTORY * self.strips * BINS * 4];
+        for (t, snapshot) in self.snapshots.iter().enumerate() {
+            for (strip, values) in snapshot.iter().enumerate() {
+                let (min, max) = self.ranges[strip];
+                let mut counts = [0usize; BINS];
+                for value in values {
+                    let bin = ((value - min) / (max - min) * BINS as f32) as usize;
+                    counts[bin.min(BINS - 1)] += 1;
+                }
+                let most = counts.iter().cloned().max().unwrap_or(0).max(1);
+                for (bin, count) in counts.iter().enumerate() {
+                    // The square root keeps the sparse buckets visible
+                    let heat = (*count as f32 / most as f32).sqrt();
+                    let color = color_lerp(EMPTY_COLOR, FULL_COLOR, heat);
+                    // Larger values on top
+                    let row = strip * BINS + BINS - 1 - bin;
+                    let pixel = (row * HISTORY + t) * 4;
+                    bytes[pixel..pixel + 4].copy_from_slice(&[
+                        (color.r * 255.) as u8,
+                        (color.g * 255.) as u8,
+                        (color.b * 255.) as u8,
+                        255,
+                    ]);
+                }
+            }
+        }
+        self.texture.update(&macroquad::texture::Image {
+            bytes,
+            width: HISTORY as u16,
+            height: (self.strips * BINS) as u16,
+        });
+    }
+
+    /// Draws every strip with its label in the box at `x`, `y`.
+    pub fn draw(&self, x: f32, y: f32, width: f32, height: f32) {
+        draw_text(
+            "histograms | w = weights, b = biases, a = activations",
+            x,
+            y + 15.,
+            20.,
+            TEXT_COLOR,
+        );
+        if self.snapshots.is_empty() {
+            draw_text("collecting...", x, y + 40., 20., TEXT_COLOR);
+            return;
+        }
+
+        let label_width = measure_text("99 w", None, 16, 1.).width + 6.;
+        // Room for the epochs below
+        let (px, py) = (x + label_width, y + 20.);
+        let (pw, ph) = (width - label_width, height - 40.);
+        draw_texture_ex(
+            self.texture,
+            px,
+            py,
+            WHITE,
+            DrawTextureParams {
+                dest_size: Some(vec2(pw, ph)),
+                ..Default::default()
+            },
+        );
+
+        let strip_height = ph / self.strips as f32;
+        for strip in 0..self.strips {
+            let top = py + strip as f32 * strip_height;
+            draw_rectangle_lines(px, top, pw, strip_height, 1., GRAY);
+            let label = format!("{} {}", strip / 3 + 1, ["w", "b", "a"][strip % 3]);
+            draw_text(&label, x, top + strip_height / 2. + 5., 16., TEXT_COLOR);
+            if strip_height >= 24. {
+                let (min, max) = self.ranges[strip];
+                draw_text(&format!("{:.2}", max), px + 2., top + 12., 14., TEXT_COLOR);
+                draw_text(
+                    &format!("{:.2}", min),
+                    px + 2.,
+                    top + strip_height - 3.,
+                    14.,
+                    TEXT_COLOR,
+                );
+            }
+        }
+
+        // The texture fills up from the left as epochs go by
+        let first = format!("epoch {}", self.epochs[0]);
+        draw_text(&first, px, py + ph + 15., 16., TEXT_COLOR);
+        let last = format!("{}", self.epochs[self.epochs.len() - 1]);
+        let filled = self.snapshots.len() as f32 / HISTORY as f32;
+        let last_width = measure_text(&last, None, 16, 1.).width;
+        let lx = (px + pw * filled - last_width)
+            .min(px + pw - last_width)
+            .max(px + measure_text(&first, None, 16, 1.).width + 8.);
+        draw_text(&last, lx, py + ph + 15., 16., TEXT_COLOR);
+    }
+}
+
+// A new one is made on every reset, and textures aren't freed on their own
+impl Drop for Histograms {
+    fn drop(&mut self) {
+        self.texture.delete();
+    }
+}
@@ -6,14 +6,35 @@ use std::{
     thread,
 };
 
-use framework::{sigmoidf, Mat, NN};
+use framework::{
+    config::Config,
+    image::Image,
+    preprocess::Pipeline,
+    sigmoidf,
+    trainer::{Callback, Flow, Trainer},
+    Mat, NN,
+};
 use macroquad::prelude::*;
 
+mod boundary;
+mod cli;
 mod draw;
-use draw::{draw_frame, Renderinfo};
+mod graph;
+mod histogram;
+mod picker;
+mod training;
+mod upscale;
+use boundary::Boundary;
+use draw::{column_ranges, draw_frame, Renderinfo, View};
+use histogram::Histograms;
+use picker::{Choice, Data, DatasetPicker};
+use training::{new_trainer, print_report};
+use upscale::Upscaler;
 
-const EPOCH_MAX: i32 = 100_000;
-const LEARNING_RATE: f32 = 1.;
+// Pixels per gradient step when learning an image
+const IMAGE_BATCH_SIZE: usize = 32;
+const EARLY_STOPPING_PATIENCE: usize = 1000;
+const EARLY_STOPPING_MIN_DELTA: f32 = 1e-4;
 
 const WINDOW_WIDTH: i32 = 800;
 const WINDOW_HEIGHT: i32 = 600;
@@ -21,6 +42,7 @@ const WINDOW_HEIGHT: i32 = 600;
 const BACKGROUND_COLOR: Color = BLACK;
 const TEXT_COLOR: Color = WHITE;
 const LINE_COLOR: Color = RED;
+const VAL_LINE_COLOR: Color = SKYBLUE;
 
 #[derive(PartialEq)]
 enum Signal {
@@ -29,128 +51,294 @@ enum Signal {
     Stop,
 }
 
-#[macroquad::main(window_conf)]
-async fn main() {
-    let nn_structure = &[2, 4, 4, 1];
-    let nn = Arc::new(Mutex::new(NN::new(nn_structure)));
-    let gradient = NN::new(nn_structure);
+/// Mirrors the trainer's progress into the state shared with the render loop
+/// and obeys the pause/stop signals sent from it.
+struct GuiCallback {
+    nn: Arc<Mutex<NN>>,
+    info: Arc<Mutex<Renderinfo>>,
+    rx: Receiver<Signal>,
+    time_elapsed: i64,
+    // set when the render loop told us to stop, the shared state then belongs to the next run
+    stopped: bool,
+}
+
+impl GuiCallback {
+    // Handles a pause or stop from the render loop, blocking while paused
+    fn check_signals(&mut self) -> Flow {
+        if self.stopped {
+            return Flow::Stop;
+        }
+        if let Ok(signal) = self.rx.try_recv() {
+            match signal {
+                Signal::Pause => {
+                    self.info.lock().unwrap().paused = true;
+
+                    while let Ok(signal) = self.rx.recv() {
+                        if signal == Signal::Resume {
+                            self.info.lock().unwrap().paused = false;
+                            break;
+                        } else if signal == Signal::Stop {
+                            self.stopped = true;
+                            return Flow::Stop;
+                        }
+                    }
+                }
+                Signal::Stop => {
+                    self.stopped = true;
+                    return Flow::Stop;
+                }
+                _ => {}
+            }
+        }
+        Flow::Continue
+    }
+}
+
+impl Callback for GuiCallback {
+    // Checked between batches too, so a reset doesn't wait for a long epoch
+    fn on_batch_end(&mut self, _trainer: &Trainer, _batch: usize) -> Flow {
+        self.check_signals()
+    }
+
+    fn on_epoch_end(&mut self, trainer: &Trainer) -> Flow {
+        if self.check_signals() == Flow::Stop {
+            return Flow::Stop;
+        }
+
+        {
+            let mut info = self.info.lock().unwrap();
+            info.epoch = trainer.epoch as i32;
+            info.training_time =
+                (chrono::Utc::now().timestamp_millis() - self.time_elapsed) as f32 / 1000.0;
+            info.learning_rate = trainer.learning_rate;
+            info.val_cost = trainer.val_cost;
+            info.gradient = trainer.gradient.clone();
+        }
+        *self.nn.lock().unwrap() = trainer.nn.clone();
+
+        Flow::Continue
+    }
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    // `--headless` trains on what the window would have without opening it
+    if let Some(i) = args.iter().position(|arg| arg == "--headless") {
+        let mut args = args.clone();
+        args.remove(i);
+        if let Err(err) = cli::run("train", &args) {
+            eprintln!("error: {}", err);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    // Subcommands run without a window
+    if let Some(command) = args.first() {
+        if cli::is_command(command) {
+            if let Err(err) = cli::run(command, &args[1..]) {
+                eprintln!("error: {}", err);
+                std::process::exit(1);
+            }
+            return;
+        }
+    }
+
+    macroquad::Window::from_config(window_conf(), gui(args));
+}
+
+async fn gui(mut args: Vec<String>) {
+    // `--config path` loads the run's settings from a JSON file, see
+    // framework::config::Config
+    let mut config = Config::default();
+    if let Some(i) = args.iter().position(|arg| arg == "--config") {
+        if i + 1 >= args.len() {
+            eprintln!("--config needs a path");
+            std::process::exit(1);
+        }
+        let path = args.remove(i + 1);
+        args.remove(i);
+        config = match Config::load(&path) {
+            Ok(config) => config,
+            Err(err) => {
+                eprintln!("{}: {}", path, err);
+                std::process::exit(1);
+            }
+        };
+    }
+    // The config's data set is either a built-in one or a file to load
+    let mut builtin = None;
+    if let Some(dataset) = &config.dataset {
+        if std::path::Path::new(dataset).exists() {
+            args.push(dataset.clone());
+        } else {
+            builtin = Some(dataset.clone());
+        }
+    }
+
+    // CSV files and IDX pairs passed as arguments can be picked in the
+    // window, any other path is an image to learn. Several images are
+    // learned together so the network can morph between them
+    let mut loaded = Vec::new();
+    let mut images: Vec<Image> = Vec::new();
+    let (idx, args): (Vec<String>, Vec<String>) =
+        args.into_iter().partition(|path| picker::is_idx(path));
+    if !idx.is_empty() {
+        match Choice::from_paths(&idx) {
+            Ok(choice) => loaded.push(choice),
+            Err(err) => {
+                eprintln!("{}", err);
+                std::process::exit(1);
+            }
+        }
+    }
+    for path in args {
+        let result = if path.ends_with(".csv") {
+            Choice::csv(&path)
+                .map(|choice| loaded.push(choice))
+                .map_err(|err| err.to_string())
+        } else {
+            Image::load(&path)
+                .map(|image| images.push(image))
+                .map_err(|err| err.to_string())
+        };
+        if let Err(err) = result {
+            eprintln!("{}: {}", path, err);
+            std::process::exit(1);
+        }
+    }
+    if images
+        .iter()
+        .any(|image| image.channels != images[0].channels)
+    {
+        eprintln!("Images must be all grayscale or all color");
+        std::process::exit(1);
+    }
+    let mut upscaler = if images.is_empty() {
+        None
+    } else {
+        loaded.push(Choice::images(images.len()));
+        match Upscaler::new(images) {
+            Ok(upscaler) => Some(upscaler),
+            Err(err) => {
+                eprintln!("{}", err);
+                std::process::exit(1);
+            }
+        }
+    };
+
+    let mut picker = DatasetPicker::new(loaded);
+    if let Some(name) = builtin {
+        if !picker.select(&name) {
+            eprintln!(
+                "`dataset`: no file or built-in data set named `{}`, expected a path or one of: {}",
+                name,
+                picker.names().join(", ")
+            );
+            std::process::exit(1);
+        }
+    }
+    let nn = Arc::new(Mutex::new(NN::new(&[1])));
+    let mut view = View::default();
 
     'reset: loop {
-        // XOR Example
-        let t_input = Mat::new(&[&[0.0, 0.0], &[0.0, 1.0], &[1.0, 0.0], &[1.0, 1.0]]);
-        let t_output = Mat::new(&[&[0.0], &[1.0], &[1.0], &[0.0]]);
-
-        // Opposite example
-        // let t_input = Mat::new(&[
-        //     &[1.0],
-        //     &[0.9],
-        //     &[0.8],
-        //     &[0.7],
-        //     &[0.6],
-        //     &[0.5],
-        //     &[0.4],
-        //     &[0.3],
-        //     &[0.2],
-        //     &[0.1],
-        //     &[0.0],
-        // ]);
-
-        // let t_output = Mat::new(&[
-        //     &[0.0],
-        //     &[0.1],
-        //     &[0.2],
-        //     &[0.3],
-        //     &[0.4],
-        //     &[0.5],
-        //     &[0.6],
-        //     &[0.7],
-        //     &[0.8],
-        //     &[0.9],
-        //     &[1.0],
-        // ]);
-
-        let mut gradient = gradient.clone();
+        if let Some(upscaler) = &upscaler {
+            upscaler.reset();
+        }
+        let image_mode = matches!(picker.current().data, Data::Images);
+        let (t_input, t_output) = match (&picker.current().data, &upscaler) {
+            (Data::Table(t_input, t_output), _) => (t_input.clone(), t_output.clone()),
+            (Data::Images, Some(upscaler)) => upscaler.training(),
+            (Data::Images, None) => unreachable!("image choice without images"),
+        };
+        // The window draws the rows as they were loaded, so it trains
+        // without the config's pipelines
+        let (mut trainer, t_input, t_output) = new_trainer(
+            &config,
+            picker.current(),
+            t_input,
+            t_output,
+            &mut Pipeline::default(),
+            &mut Pipeline::default(),
+        );
+        // The widths change with the data set
+        let arch: Vec<usize> = trainer.nn.activations.iter().map(|a| a.cols).collect();
+        *nn.lock().unwrap() = trainer.nn.clone();
+        // Two inputs can be drawn as a plane, images already have their panel
+        let boundary = if image_mode {
+            None
+        } else {
+            Boundary::new(&t_input, &t_output)
+        };
+        let mut histograms = Histograms::new(&trainer.nn, &t_input);
 
         let (tx, rx): (Sender<Signal>, Receiver<Signal>) = channel();
 
         let mut paused = false;
         let time_elapsed = chrono::Utc::now().timestamp_millis();
 
-        let info: Arc<Mutex<Renderinfo>>;
-        {
-            // Calculate first cost for creating the struct
-            let mut nn = nn.lock().unwrap();
-            NN::randomize(&mut nn, -1.0, 1.0);
-            let cost = NN::cost(&nn, &t_input, &t_output);
-            println!("Initial cost: {}", cost);
-            info = Arc::new(Mutex::new(Renderinfo {
-                epoch: 0,
-                cost,
-                t_input: t_input.clone(),
-                t_output: t_output.clone(),
-                training_time: 0.0,
-                cost_history: vec![cost],
-                paused,
-            }));
-        }
+        println!("Initial cost: {}", trainer.cost);
+        let info = Arc::new(Mutex::new(Renderinfo {
+            epoch: 0,
+            cost: trainer.cost,
+            t_input: t_input.clone(),
+            t_output: t_output.clone(),
+            input_ranges: column_ranges(&t_input),
+            training_time: 0.0,
+            learning_rate: config.learning_rate,
+            epochs: config.epochs,
+            cost_history: vec![trainer.cost],
+            epoch_history: vec![0],
+            val_cost: trainer.val_cost,
+            val_cost_history: trainer.val_cost.into_iter().collect(),
+            paused,
+            finished: false,
+            dataset: picker.label(),
+            gradient: NN::new(&arch),
+        }));
 
         clear_background(BACKGROUND_COLOR);
         {
+            // A copy, so training isn't held up while the frame is drawn
+            let snapshot = nn.lock().unwrap().clone();
             let mut info = info.lock().unwrap();
-            draw_frame(&nn.lock().unwrap(), &mut info);
+            draw_frame(
+                snapshot,
+                &mut info,
+                &view,
+                upscaler.as_ref().filter(|_| image_mode),
+                boundary.as_ref(),
+                &mut histograms,
+            );
         }
         next_frame().await;
 
         // TRAINING
-        let nn_clone = Arc::clone(&nn);
-        let info_clone = Arc::clone(&info);
-
-        let _training_thread = thread::spawn(move || {
-            'training: for i in 0..=EPOCH_MAX {
-                if let Ok(signal) = rx.try_recv() {
-                    match signal {
-                        Signal::Pause => {
-                            let mut info = info_clone.lock().unwrap();
-                            info.paused = true;
-                            drop(info);
-
-                            while let Ok(signal) = rx.recv() {
-                                if signal == Signal::Resume {
-                                    let mut info = info_clone.lock().unwrap();
-                                    info.paused = false;
-                                    drop(info);
-                                    break;
-                                } else if signal == Signal::Stop {
-                                    break 'training;
-                                }
-                            }
-                        }
-                        Signal::Stop => {
-                            break 'training;
-                        }
-                        _ => {}
-                    }
-                }
+        let mut gui = GuiCallback {
+            nn: Arc::clone(&nn),
+            info: Arc::clone(&info),
+            rx,
+            time_elapsed,
+            stopped: false,
+        };
 
-                {
-                    let mut info = info_clone.lock().unwrap();
-                    info.epoch = i;
-                    info.t_input = t_input.clone();
-                    info.t_output = t_output.clone();
-                    info.training_time =
-                        (chrono::Utc::now().timestamp_millis() - time_elapsed) as f32 / 1000.0;
-                }
-
-                {
-                    let mut nn = nn_clone.lock().unwrap();
-                    NN::backprop(&mut nn, &mut gradient, &t_input, &t_output);
-                    NN::learn(&mut nn, &gradient, LEARNING_RATE);
-                }
+        let epochs = config.epochs;
+        let training_thread = thread::spawn(move || {
+            trainer.fit(epochs, &mut [&mut gui]);
+            println!("Training time: {}", gui.info.lock().unwrap().training_time);
+            if gui.stopped {
+                return;
             }
-            println!(
-                "Training time: {}",
-                info_clone.lock().unwrap().training_time
-            );
+
+            print_report(&trainer, &t_input, &t_output);
+
+            // Show the restored best weights rather than the last ones
+            *gui.nn.lock().unwrap() = trainer.nn.clone();
+            let mut info = gui.info.lock().unwrap();
+            info.cost = trainer.cost;
+            info.val_cost = trainer.val_cost;
+            info.finished = true;
         });
 
         loop {
@@ -159,15 +347,25 @@ async fn main() {
                 std::process::exit(0);
             }
 
-            // Reset?
-            if is_key_pressed(KeyCode::R) {
-                // Stop the training thread
+            // Reset or switch to another data set?
+            let reset = is_key_pressed(KeyCode::R);
+            let switched = picker.handle_input();
+            if reset || switched {
+                // Stop the training thread and wait for it, so it can't touch
+                // the next run's network
                 let _ = tx.send(Signal::Stop);
-                println!("Reset");
+                let _ = training_thread.join();
+                if switched {
+                    println!("Dataset: {}", picker.current().name);
+                } else {
+                    println!("Reset");
+                }
                 // Restart the program
                 continue 'reset;
             }
 
+            view.handle_input(info.lock().unwrap().t_input.rows);
+
             // Pause/Resume?
             if is_key_pressed(KeyCode::P) {
                 if paused {
@@ -183,10 +381,46 @@ async fn main() {
                 }
             }
 
+            if let Some(upscaler) = upscaler.as_mut().filter(|_| image_mode) {
+                // Save the upscaled image?
+                if is_key_pressed(KeyCode::S) {
+                    match upscaler.export(&nn.lock().unwrap()) {
+                        Ok(path) => println!("Saved {}", path),
+                        Err(err) => eprintln!("Saving failed: {}", err),
+                    }
+                }
+
+                // Save the interpolation frames?
+                if is_key_pressed(KeyCode::F) && upscaler.images.len() > 1 {
+                    match upscaler.export_frames(&nn.lock().unwrap()) {
+                        Ok(dir) => println!("Saved frames to {}", dir),
+                        Err(err) => eprintln!("Saving failed: {}", err),
+                    }
+                }
+
+                upscaler.handle_input();
+                if is_key_pressed(KeyCode::Equal) || is_key_pressed(KeyCode::KpAdd) {
+                    upscaler.scale += 1;
+                }
+                if (is_key_pressed(KeyCode::Minus) || is_key_pressed(KeyCode::KpSubtract))
+                    && upscaler.scale > 1
+                {
+                    upscaler.scale -= 1;
+                }
+            }
+
             clear_background(BACKGROUND_COLOR);
             {
+                let snapshot = nn.lock().unwrap().clone();
                 let mut info = info.lock().unwrap();
-                draw_frame(&nn.lock().unwrap(), &mut info);
+                draw_frame(
+                    snapshot,
+                    &mut info,
+                    &view,
+                    upscaler.as_ref().filter(|_| image_mode),
+                    boundary.as_ref(),
+                    &mut histograms,
+                );
             }
             next_frame().await;
         }
@@ -0,0 +1,276 @@
+use std::path::Path;
+
+use framework::{
+    csv::{self, CsvError, CsvOptions, Schema},
+    generate,
+    idx::{Idx, IdxError},
+    image::interpolation_training,
+};
+
+use super::{is_key_down, is_key_pressed, Image, KeyCode, Mat, IMAGE_BATCH_SIZE};
+
+// Points in each generated 2d problem
+const POINT_SAMPLES: usize = 200;
+// Every generated problem looks the same from run to run
+const GENERATOR_SEED: u64 = 1;
+// Digits per gradient step on an MNIST style data set
+const IDX_BATCH_SIZE: usize = 32;
+const DIGITS: usize = 10;
+
+/// Whether `path` looks like an uncompressed IDX file, named like MNIST's
+/// `train-images-idx3-ubyte` or ending in `.idx`.
+pub fn is_idx(path: &str) -> bool {
+    path.ends_with("-ubyte") || path.ends_with(".idx")
+}
+
+pub enum Data {
+    Table(Mat, Mat),
+    /// The images passed on the command line, trained on by the upscaler.
+    Images,
+}
+
+/// A problem the visualizer can train on, with the hidden layers its
+/// network gets.
+pub struct Choice {
+    pub name: String,
+    pub data: Data,
+    pub hidden: Vec<usize>,
+    /// Samples per gradient step, 0 for the whole data set.
+    pub batch_size: usize,
+    /// How a CSV file's columns were encoded, saved with models trained on it.
+    pub schema: Option<Schema>,
+    /// Width, height and channels of every input row when the rows are whole
+    /// images, which can be augmented.
+    pub image: Option<(usize, usize, usize)>,
+}
+
+impl Choice {
+    fn table(name: &str, (t_input, t_output): (Mat, Mat), hidden: &[usize]) -> Choice {
+        Choice {
+            name: name.to_owned(),
+            data: Data::Table(t_input, t_output),
+            hidden: hidden.to_vec(),
+            batch_size: 0,
+            schema: None,
+            image: None,
+        }
+    }
+
+    /// A CSV file, or else an image learned pixel by pixel.
+    pub fn load(path: &str) -> Result<Choice, String> {
+        Choice::from_paths(&[path.to_owned()])
+    }
+
+    /// The one CSV file, an IDX images and labels pair, or all the images
+    /// learned together the way the upscaler learns them, as rows.
+    pub fn from_paths(paths: &[String]) -> Result<Choice, String> {
+        if let [path] = paths {
+            if path.ends_with(".csv") {
+                return Choice::csv(path).map_err(|err| format!("{}: {}", path, err));
+            }
+        }
+        if paths.iter().any(|path| is_idx(path)) {
+            return match paths {
+                [images, labels] if is_idx(images) && is_idx(labels) => {
+                    Choice::mnist(images, labels)
+                }
+                _ => Err("give IDX files as an images file and its labels file".to_owned()),
+            };
+        }
+
+        let mut images = Vec::new();
+        for path in paths {
+            if path.ends_with(".csv") {
+                return Err("give either one CSV file or images".to_owned());
+            }
+            images.push(Image::load(path).map_err(|err| format!("{}: {}", path, err))?);
+        }
+        if images
+            .iter()
+            .any(|image| image.channels != images[0].channels)
+        {
+            return Err("images must be all grayscale or all color".to_owned());
+        }
+        // like the upscaler, which renders them all at the first one's size
+        if images
+            .iter()
+            .any(|image| (image.width, image.height) != (images[0].width, images[0].height))
+        {
+            return Err("images must all have the same size".to_owned());
+        }
+
+        let rows = match images.as_slice() {
+            [image] => image.to_training(),
+            images => interpolation_training(images),
+        };
+        Ok(Choice {
+            batch_size: IMAGE_BATCH_SIZE,
+            ..Choice::table(&Choice::images(images.len()).name, rows, &[16, 16])
+        })
+    }
+
+    /// A CSV file with a header, its last column being the target.
+    pub fn csv<P: AsRef<Path>>(path: P) -> Result<Choice, CsvError> {
+        let data = csv::load(&path, &CsvOptions::default())?;
+        let name = path
+            .as_ref()
+            .file_name()
+            .map_or_else(String::new, |name| name.to_string_lossy().into_owned());
+        Ok(Choice {
+            schema: Some(data.schema),
+            ..Choice::table(&name, (data.t_input, data.t_output), &[8, 8])
+        })
+    }
+
+    /// An MNIST style pair of IDX files, the images as normalized pixel rows
+    /// and the labels as one-hot digits.
+    pub fn mnist(images_path: &str, labels_path: &str) -> Result<Choice, String> {
+        // framework::idx::load_mnist, keeping the image size
+        let load = || -> Result<_, IdxError> {
+            let images = Idx::load(images_path)?;
+            let labels = Idx::load(labels_path)?;
+            if images.items() != labels.items() {
+                return Err(IdxError::CountMismatch {
+                    images: images.items(),
+                    labels: labels.items(),
+                });
+            }
+            let rows = (images.to_inputs()?, labels.to_one_hot(DIGITS)?);
+            Ok((rows, images.image_dims()))
+        };
+        let (rows, image) =
+            load().map_err(|err| format!("{} and {}: {}", images_path, labels_path, err))?;
+
+        let name = Path::new(images_path)
+            .file_name()
+            .map_or_else(String::new, |name| name.to_string_lossy().into_owned());
+        Ok(Choice {
+            batch_size: IDX_BATCH_SIZE,
+            image,
+            ..Choice::table(&name, rows, &[32])
+        })
+    }
+
+    pub fn images(count: usize) -> Choice {
+        Choice {
+            name: format!("{} image{}", count, if count == 1 { "" } else { "s" }),
+            data: Data::Images,
+            hidden: vec![16, 16],
+            batch_size: IMAGE_BATCH_SIZE,
+            schema: None,
+            image: None,
+        }
+    }
+}
+
+/// The built-in problems followed by the ones loaded from files, switched
+/// between with `d` and `shift + d`.
+pub struct DatasetPicker {
+    pub choices: Vec<Choice>,
+    pub selected: usize,
+}
+
+impl DatasetPicker {
+    /// Starts on the first loaded choice, or on XOR without any.
+    pub fn new(loaded: Vec<Choice>) -> DatasetPicker {
+        let mut choices = builtin();
+        let selected = if loaded.is_empty() { 0 } else { choices.len() };
+        choices.extend(loaded);
+        DatasetPicker { choices, selected }
+    }
+
+    pub fn current(&self) -> &Choice {
+        &self.choices[self.selected]
+    }
+
+    /// Finds a built-in choice by name, ignoring case.
+    pub fn builtin(name: &str) -> Option<Choice> {
+        builtin()
+            .into_iter()
+            .find(|choice| choice.name.eq_ignore_ascii_case(name))
+    }
+
+    /// Selects the choice called `name`, ignoring case. Returns false when
+    /// there's none.
+    pub fn select(&mut self, name: &str) -> bool {
+        match self
+            .choices
+            .iter()
+            .position(|choice| choice.name.eq_ignore_ascii_case(name))
+        {
+            Some(index) => {
+                self.selected = index;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        self.choices
+            .iter()
+            .map(|choice| choice.name.as_str())
+            .collect()
+    }
+
+    /// Returns true when the selection changed.
+    pub fn handle_input(&mut self) -> bool {
+        if !is_key_pressed(KeyCode::D) {
+            return false;
+        }
+
+        let count = self.choices.len();
+        self.selected = if is_key_down(KeyCode::LeftShift) || is_key_down(KeyCode::RightShift) {
+            (self.selected + count - 1) % count
+        } else {
+            (self.selected + 1) % count
+        };
+        true
+    }
+
+    pub fn label(&self) -> String {
+        format!(
+            "{} ({}/{})",
+            self.current().name,
+            self.selected + 1,
+            self.choices.len()
+        )
+    }
+}
+
+fn builtin() -> Vec<Choice> {
+    vec![
+        Choice::table("XOR", generate::xor(2), &[4, 4]),
+        Choice::table("AND", generate::and(2), &[4, 4]),
+        Choice::table("OR", generate::or(2), &[4, 4]),
+        Choice::table("NAND", generate::nand(2), &[4, 4]),
+        Choice::table("2-bit adder", generate::adder(2), &[8, 8]),
+        Choice::table("2-bit multiplier", generate::multiplier(2), &[8, 8]),
+        Choice::table(
+            "Spirals",
+            generate::spirals(POINT_SAMPLES, 1.0, 0.01, GENERATOR_SEED),
+            &[16, 16],
+        ),
+        Choice::table(
+            "Circles",
+            generate::circles(POINT_SAMPLES, 0.5, 0.02, GENERATOR_SEED),
+            &[8, 8],
+        ),
+        Choice::table(
+            "Moons",
+            generate::moons(POINT_SAMPLES, 0.02, GENERATOR_SEED),
+            &[8, 8],
+        ),
+        Choice::table(
+            "Blobs",
+            generate::blobs(POINT_SAMPLES, 3, 0.05, GENERATOR_SEED),
+            &[8],
+        ),
+        Choice::table("Opposite", generate::opposite(11), &[4]),
+        Choice::table(
+            "Sine",
+            generate::sine(POINT_SAMPLES / 4, 0.02, GENERATOR_SEED),
+            &[8, 8],
+        ),
+    ]
+}
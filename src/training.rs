@@ -0,0 +1,225 @@
+use std::time::{Duration, Instant};
+
+use framework::{
+    augment::{Augmentations, Augmented},
+    config::Config,
+    dataset::{Dataset, InMemory},
+    loss::{CrossEntropy, Loss, Mse},
+    metrics::{Classification, Regression},
+    optimizer::{Momentum, Optimizer, Sgd},
+    preprocess::Pipeline,
+    schedule::{ReduceOnPlateau, Schedule},
+    split,
+    trainer::{Callback, EarlyStopping, Flow, Monitor, Trainer},
+    Mat, NN,
+};
+
+use super::{picker::Choice, EARLY_STOPPING_MIN_DELTA, EARLY_STOPPING_PATIENCE};
+
+// How often headless training reports its progress
+const PROGRESS_INTERVAL: Duration = Duration::from_secs(1);
+// Share of the last step the momentum optimizer keeps going
+const MOMENTUM: f32 = 0.9;
+
+/// Sets up training the same way for the window and the command line on
+/// `choice`'s rows: the validation split, the `input` and `output` pipelines
+/// fitted on the rows left for training, augmentation of image rows, fresh
+/// weights sized to the transformed rows around the hidden layers, the
+/// learning rate schedule, batching and early stopping.
+///
+/// Also returns the transformed rows left for training once the validation
+/// rows are held out.
+pub fn new_trainer(
+    config: &Config,
+    choice: &Choice,
+    mut t_input: Mat,
+    mut t_output: Mat,
+    input: &mut Pipeline,
+    output: &mut Pipeline,
+) -> (Trainer, Mat, Mat) {
+    // macroquad's generator is never seeded, so the split and augmentation
+    // would be the same on every run
+    let seed = config
+        .seed
+        .unwrap_or_else(|| chrono::Utc::now().timestamp_nanos_opt().unwrap_or_default() as u64);
+
+    let mut validation = None;
+    if config.validation > 0.0 {
+        let split = split::holdout(&t_input, &t_output, config.validation, seed);
+        // Too few rows to spare any, train on all of them
+        if split.holdout_input.rows > 0 && split.train_input.rows > 0 {
+            t_input = split.train_input;
+            t_output = split.train_output;
+            validation = Some((split.holdout_input, split.holdout_output));
+        }
+    }
+
+    // The validation rows are left out of the fit like any unseen rows
+    let t_input = input.fit_transform(&t_input);
+    let t_output = output.fit_transform(&t_output);
+    let validation = validation.map(|(v_input, v_output)| {
+        InMemory::new(input.transform(&v_input), output.transform(&v_output))
+    });
+
+    let mut arch = vec![t_input.cols];
+    arch.extend(config.hidden.as_deref().unwrap_or(&choice.hidden));
+    arch.push(t_output.cols);
+    let mut nn = NN::new(&arch);
+    match config.seed {
+        Some(seed) => NN::randomize_seeded(&mut nn, -1.0, 1.0, seed),
+        None => NN::randomize(&mut nn, -1.0, 1.0),
+    }
+
+    let mut data: Box<dyn Dataset + Send> =
+        Box::new(InMemory::new(t_input.clone(), t_output.clone()));
+    // Only whole images can be turned, shifted and so on, and only before a
+    // pipeline changes what the columns mean
+    if let Some(image) = choice.image {
+        if config.augmentation != Augmentations::default() && input.is_empty() {
+            data = Box::new(Augmented::new(
+                data,
+                image,
+                config.augmentation.clone(),
+                seed,
+            ));
+        }
+    }
+
+    let mut trainer = Trainer::with_dataset(nn, data);
+    trainer.set_loss(loss(&config.loss));
+    trainer.optimizer = optimizer(&config.optimizer);
+    trainer.schedule = learning_rate_schedule(config.learning_rate);
+    trainer.batch_size = config.batch_size.unwrap_or(choice.batch_size);
+    let mut monitor = Monitor::TrainCost;
+    if let Some(validation) = validation {
+        trainer.set_validation(Box::new(validation));
+        monitor = Monitor::ValidationCost;
+    }
+    trainer.early_stopping = Some(EarlyStopping::new(
+        monitor,
+        EARLY_STOPPING_PATIENCE,
+        EARLY_STOPPING_MIN_DELTA,
+    ));
+
+    (trainer, t_input, t_output)
+}
+
+// The config only holds names it knows
+fn loss(name: &str) -> Box<dyn Loss + Send> {
+    match name {
+        "cross_entropy" => Box::new(CrossEntropy),
+        _ => Box::new(Mse),
+    }
+}
+
+fn optimizer(name: &str) -> Box<dyn Optimizer + Send> {
+    match name {
+        "momentum" => Box::new(Momentum::new(MOMENTUM)),
+        _ => Box::new(Sgd),
+    }
+}
+
+// Swap this out for any other `framework::schedule` to change how the
+// learning rate evolves during training.
+fn learning_rate_schedule(learning_rate: f32) -> Box<dyn Schedule + Send> {
+    let mut schedule = ReduceOnPlateau::new(learning_rate, 0.5, 1000);
+    schedule.min_delta = 1e-6;
+    schedule.min_rate = learning_rate / 100.0;
+    Box::new(schedule)
+}
+
+/// Targets that are all 0 or 1 are classes, anything else is regression.
+pub fn is_classification(t_output: &Mat) -> bool {
+    t_output
+        .data
+        .iter()
+        .flatten()
+        .all(|val| *val == 0.0 || *val == 1.0)
+}
+
+/// Prints how training ended and how well the network fits the given rows.
+pub fn print_report(trainer: &Trainer, t_input: &Mat, t_output: &Mat) {
+    if let Some(early_stopping) = &trainer.early_stopping {
+        if trainer.stopped_early {
+            println!(
+                "Stopped early at epoch {}, restored best epoch {} (cost {})",
+                trainer.epoch,
+                early_stopping.best_epoch(),
+                early_stopping.best()
+            );
+        }
+    }
+
+    if is_classification(t_output) {
+        println!(
+            "{}",
+            Classification::evaluate(&trainer.nn, t_input, t_output)
+        );
+    }
+    println!("{}", Regression::evaluate(&trainer.nn, t_input, t_output));
+}
+
+/// Prints the epoch, cost, speed and time left about once a second, for
+/// training without a window.
+pub struct Progress {
+    epochs: usize,
+    start: Instant,
+    last: Instant,
+}
+
+impl Progress {
+    pub fn new(epochs: usize) -> Progress {
+        let now = Instant::now();
+        Progress {
+            epochs,
+            start: now,
+            last: now,
+        }
+    }
+
+    /// Prints the line for the last epoch reached.
+    pub fn finish(&self, trainer: &Trainer) {
+        println!("{}", self.line(trainer, true));
+    }
+
+    fn line(&self, trainer: &Trainer, done: bool) -> String {
+        let elapsed = self.start.elapsed().as_secs_f32();
+        let speed = trainer.epoch as f32 / elapsed.max(f32::EPSILON);
+        let mut line = format!(
+            "epoch {}/{} | cost {:.6}",
+            trainer.epoch, self.epochs, trainer.cost
+        );
+        if let Some(val_cost) = trainer.val_cost {
+            line += &format!(" | validation {:.6}", val_cost);
+        }
+        line += &format!(" | {:.0} epochs/s", speed);
+        if !done {
+            let left = (self.epochs - trainer.epoch) as f32 / speed.max(f32::EPSILON);
+            line += &format!(" | ETA {}", format_duration(left));
+        } else {
+            line += &format!(" | took {}", format_duration(elapsed));
+        }
+        line
+    }
+}
+
+impl Callback for Progress {
+    fn on_epoch_end(&mut self, trainer: &Trainer) -> Flow {
+        if self.last.elapsed() >= PROGRESS_INTERVAL {
+            self.last = Instant::now();
+            println!("{}", self.line(trainer, false));
+        }
+        Flow::Continue
+    }
+}
+
+fn format_duration(secs: f32) -> String {
+    let whole = secs as u64;
+    if whole >= 3600 {
+        format!("{}h{:02}m", whole / 3600, whole % 3600 / 60)
+    } else if whole >= 60 {
+        format!("{}m{:02}s", whole / 60, whole % 60)
+    } else {
+        format!("{:.1}s", secs)
+    }
+}
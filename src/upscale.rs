@@ -0,0 +1,262 @@
+use std::cell::Cell;
+
+use framework::{
+    image::{interpolation_training, Image, ImageError},
+    Mat,
+};
+
+use super::{
+    draw::Renderinfo, draw_circle, draw_line, draw_rectangle_lines, draw_text, draw_texture_ex,
+    is_key_down, is_mouse_button_down, mouse_position, vec2, DrawTextureParams, FilterMode,
+    KeyCode, MouseButton, Rect, Texture2D, GRAY, LINE_COLOR, NN, TEXT_COLOR, WHITE,
+};
+
+// Longer side of the live preview, in network samples
+const PREVIEW_SIZE: usize = 96;
+// Frames in an exported interpolation sequence
+const FRAME_COUNT: usize = 30;
+// How far the arrow keys move the blend slider per frame
+const BLEND_STEP: f32 = 0.01;
+
+/// The images a network is learning, drawn next to the network's own
+/// rendition of them at a higher resolution.
+///
+/// With more than one image the network gets an extra blend input and a
+/// slider sweeps it to morph between the learned images.
+pub struct Upscaler {
+    pub images: Vec<Image>,
+    /// How many times the original resolution exports are rendered at.
+    pub scale: usize,
+    /// Blend input, 0 for the first image and 1 for the last.
+    pub blend: f32,
+    originals: Vec<Texture2D>,
+    preview: Texture2D,
+    preview_size: (usize, usize),
+    // the epoch, finished flag and blend the preview was rendered for
+    shown: Cell<Option<(i32, bool, u32)>>,
+    // where the slider was last drawn, for mouse input
+    slider: Cell<Rect>,
+}
+
+impl Upscaler {
+    /// Fails unless all the images have the same size, which has to fit in a
+    /// texture.
+    pub fn new(images: Vec<Image>) -> Result<Upscaler, String> {
+        assert!(!images.is_empty());
+        let (width, height) = (images[0].width, images[0].height);
+        if images
+            .iter()
+            .any(|image| (image.width, image.height) != (width, height))
+        {
+            return Err("images must all have the same size".to_owned());
+        }
+        if width > u16::MAX as usize || height > u16::MAX as usize {
+            return Err(format!(
+                "{}x{} is too large to show, the most is {} a side",
+                width,
+                height,
+                u16::MAX
+            ));
+        }
+
+        let originals = images
+            .iter()
+            .map(|image| {
+                let texture = texture(image);
+                texture.set_filter(FilterMode::Nearest);
+                texture
+            })
+            .collect();
+
+        let longest = width.max(height).max(1);
+        let preview_size = (
+            (width * PREVIEW_SIZE / longest).max(1),
+            (height * PREVIEW_SIZE / longest).max(1),
+        );
+        let preview = Image::new(preview_size.0, preview_size.1, images[0].channels)
+            .expect("loaded images have 1 or 3 channels");
+        let preview = texture(&preview);
+
+        Ok(Upscaler {
+            images,
+            scale: 4,
+            blend: 0.0,
+            originals,
+            preview,
+            preview_size,
+            shown: Cell::new(None),
+            slider: Cell::new(Rect::new(0., 0., 0., 0.)),
+        })
+    }
+
+    /// Renders the preview again on the next draw, for a new network that
+    /// may be at the same epoch as the old one.
+    pub fn reset(&self) {
+        self.shown.set(None);
+    }
+
+    pub fn training(&self) -> (Mat, Mat) {
+        if self.images.len() > 1 {
+            interpolation_training(&self.images)
+        } else {
+            self.images[0].to_training()
+        }
+    }
+
+    // The network's outputs are the images' channels
+    fn render(&self, nn: &NN, width: usize, height: usize, blend: f32) -> Image {
+        let image = if self.images.len() > 1 {
+            Image::from_nn_blended(nn, width, height, &[blend])
+        } else {
+            Image::from_nn(nn, width, height)
+        };
+        image.expect("loaded images have 1 or 3 channels")
+    }
+
+    /// Moves the blend slider with the mouse or the arrow keys.
+    pub fn handle_input(&mut self) {
+        if self.images.len() < 2 {
+            return;
+        }
+
+        if is_key_down(KeyCode::Left) {
+            self.blend -= BLEND_STEP;
+        }
+        if is_key_down(KeyCode::Right) {
+            self.blend += BLEND_STEP;
+        }
+
+        let slider = self.slider.get();
+        let (mx, my) = mouse_position();
+        let grab = slider.w > 0.
+            && Rect::new(slider.x, slider.y - 8., slider.w, 16.).contains(vec2(mx, my));
+        if is_mouse_button_down(MouseButton::Left) && grab {
+            self.blend = (mx - slider.x) / slider.w;
+        }
+
+        self.blend = self.blend.clamp(0.0, 1.0);
+    }
+
+    /// Draws the original and the network's output side by side in the box
+    /// at `x`, `y`, with the blend slider underneath when interpolating. The
+    /// preview is only rendered again once the network or the blend has
+    /// changed.
+    pub fn draw(&self, nn: &NN, info: &Renderinfo, x: f32, y: f32, width: f32, height: f32) {
+        let state = Some((info.epoch, info.finished, self.blend.to_bits()));
+        if self.shown.get() != state {
+            self.shown.set(state);
+            let rendered = self.render(nn, self.preview_size.0, self.preview_size.1, self.blend);
+            self.preview.update(&macroquad::texture::Image {
+                bytes: rendered.to_rgba8(),
+                width: rendered.width as u16,
+                height: rendered.height as u16,
+            });
+        }
+
+        let interpolating = self.images.len() > 1;
+        let slider_space = if interpolating { 30.0 } else { 0.0 };
+
+        // Fit both pictures into half of the box each, keeping the aspect ratio
+        let image = &self.images[0];
+        let aspect = image.width as f32 / image.height as f32;
+        let mut w = width / 2.0 - 10.0;
+        let mut h = w / aspect;
+        if h > height - 25.0 - slider_space {
+            h = height - 25.0 - slider_space;
+            w = h * aspect;
+        }
+
+        // The original closest to the current blend
+        let nearest = (self.blend * (self.images.len() - 1) as f32).round() as usize;
+        let original_label = if interpolating {
+            format!("image {}/{}", nearest + 1, self.images.len())
+        } else {
+            format!("{}x{}", image.width, image.height)
+        };
+        let network_label = if interpolating {
+            format!(
+                "blend {:.2} | s - save, f - frames x{}",
+                self.blend, self.scale
+            )
+        } else {
+            format!("network | s - save x{} (+/-)", self.scale)
+        };
+
+        for (i, (texture, label)) in [
+            (self.originals[nearest], original_label),
+            (self.preview, network_label),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            let px = x + i as f32 * (w + 10.0);
+            draw_texture_ex(
+                texture,
+                px,
+                y + 20.0,
+                WHITE,
+                DrawTextureParams {
+                    dest_size: Some(vec2(w, h)),
+                    ..Default::default()
+                },
+            );
+            draw_rectangle_lines(px, y + 20.0, w, h, 1.0, GRAY);
+            draw_text(&label, px, y + 15.0, 20.0, TEXT_COLOR);
+        }
+
+        if interpolating {
+            let slider = Rect::new(x, y + 20.0 + h + 20.0, 2.0 * w + 10.0, 0.0);
+            draw_line(slider.x, slider.y, slider.x + slider.w, slider.y, 2.0, GRAY);
+            for i in 0..self.images.len() {
+                let tx = slider.x + slider.w * i as f32 / (self.images.len() - 1) as f32;
+                draw_line(tx, slider.y - 5.0, tx, slider.y + 5.0, 1.0, GRAY);
+            }
+            draw_circle(slider.x + slider.w * self.blend, slider.y, 6.0, LINE_COLOR);
+            self.slider.set(slider);
+        }
+    }
+
+    /// Renders the network at `scale` times the original resolution and saves
+    /// it as a PNG, returning the file name.
+    pub fn export(&self, nn: &NN) -> Result<String, ImageError> {
+        let (width, height) = self.export_size();
+        let path = format!(
+            "upscaled-{}x{}-{}.png",
+            width,
+            height,
+            chrono::Utc::now().timestamp_millis()
+        );
+        self.render(nn, width, height, self.blend).save(&path)?;
+
+        Ok(path)
+    }
+
+    /// Saves frames sweeping the blend input from the first image to the last
+    /// into a new directory, returning its name.
+    pub fn export_frames(&self, nn: &NN) -> Result<String, ImageError> {
+        let (width, height) = self.export_size();
+        let dir = format!("frames-{}", chrono::Utc::now().timestamp_millis());
+        std::fs::create_dir(&dir)?;
+
+        for frame in 0..FRAME_COUNT {
+            let blend = frame as f32 / (FRAME_COUNT - 1) as f32;
+            self.render(nn, width, height, blend)
+                .save(format!("{}/frame-{:03}.png", dir, frame))?;
+        }
+
+        Ok(dir)
+    }
+
+    fn export_size(&self) -> (usize, usize) {
+        (
+            self.images[0].width * self.scale,
+            self.images[0].height * self.scale,
+        )
+    }
+}
+
+// Sides are checked to fit in a u16 in `Upscaler::new`, the preview's are
+// smaller still
+fn texture(image: &Image) -> Texture2D {
+    Texture2D::from_rgba8(image.width as u16, image.height as u16, &image.to_rgba8())
+}